@@ -0,0 +1,110 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::unpacker::Entry;
+
+/// The interesting bits of an Alpine `.PKGINFO`.
+#[derive(Clone, Debug, Default)]
+pub struct ApkInfo {
+    /// `pkgname`
+    pub name: String,
+    /// `pkgver`
+    pub version: String,
+    /// `pkgdesc`
+    pub description: String,
+    pub url: String,
+    pub arch: String,
+    pub license: String,
+    pub origin: String,
+    pub maintainer: String,
+    /// `builddate`, in seconds since the epoch.
+    pub build_date: Option<u64>,
+    /// `size`, installed, in bytes.
+    pub size: Option<u64>,
+    /// `depend`, which may be repeated.
+    pub depends: Vec<String>,
+    /// `provides`, which may be repeated.
+    pub provides: Vec<String>,
+    /// Every key/value pair, in file order, including those we don't otherwise understand.
+    pub fields: Vec<(String, String)>,
+}
+
+/// The path we give each gzip member, if the members look like an apk:
+/// an optional signature, a control segment with `.PKGINFO`, then the data.
+pub fn segment_names<P: AsRef<Path>>(members: &[P]) -> Option<Vec<&'static str>> {
+    let firsts = members
+        .iter()
+        .map(first_tar_path)
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+
+    let signed = firsts[0].starts_with(b".SIGN.");
+    let expected_len = if signed { 3 } else { 2 };
+    if firsts.len() != expected_len || b".PKGINFO" != &firsts[expected_len - 2][..] {
+        return None;
+    }
+
+    Some(if signed {
+        vec!["signature", "control", "data"]
+    } else {
+        vec!["control", "data"]
+    })
+}
+
+fn first_tar_path<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut archive = tar::Archive::new(file);
+    let mut entries = archive.entries().ok()?;
+    let first = entries.next()?.ok()?;
+    let path = first.path_bytes();
+    Some(path.strip_prefix(b"./").unwrap_or(&path).to_vec())
+}
+
+/// Find and parse the `.PKGINFO` inside an apk's `control` segment.
+pub fn identify(children: &[Entry]) -> Option<ApkInfo> {
+    let control = children
+        .iter()
+        .find(|entry| b"control" == &entry.local.path[..])?;
+    let pkginfo = crate::package::read_child(control.children.entries(), b".PKGINFO")?;
+    Some(parse_pkginfo(pkginfo))
+}
+
+pub fn parse_pkginfo<R: Read>(mut from: R) -> ApkInfo {
+    let mut text = Vec::new();
+    if from.read_to_end(&mut text).is_err() {
+        return ApkInfo::default();
+    }
+
+    let mut info = ApkInfo::default();
+
+    for line in String::from_utf8_lossy(&text).lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.find(" = ") {
+            Some(pos) => (&line[..pos], &line[pos + 3..]),
+            None => continue,
+        };
+
+        let value = value.to_string();
+        match key {
+            "pkgname" => info.name = value.clone(),
+            "pkgver" => info.version = value.clone(),
+            "pkgdesc" => info.description = value.clone(),
+            "url" => info.url = value.clone(),
+            "arch" => info.arch = value.clone(),
+            "license" => info.license = value.clone(),
+            "origin" => info.origin = value.clone(),
+            "maintainer" => info.maintainer = value.clone(),
+            "builddate" => info.build_date = value.parse().ok(),
+            "size" => info.size = value.parse().ok(),
+            "depend" => info.depends.push(value.clone()),
+            "provides" => info.provides.push(value.clone()),
+            _ => (),
+        }
+
+        info.fields.push((key.to_string(), value));
+    }
+
+    info
+}
@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::io::Read;
+
+use crate::digest;
+use crate::package;
+use crate::package::Package;
+use crate::package::Verification;
+use crate::unpacker::Entry;
+
+/// What a `.deb` says about itself in its `control.tar`.
+#[derive(Clone, Debug, Default)]
+pub struct DebInfo {
+    pub control: DebControl,
+    /// From `md5sums`, if present.
+    pub md5sums: Vec<Md5Sum>,
+    /// From `conffiles`, if present: absolute paths.
+    pub conffiles: Vec<String>,
+}
+
+/// The `control` file of a binary package.
+#[derive(Clone, Debug, Default)]
+pub struct DebControl {
+    pub package: String,
+    pub version: String,
+    pub architecture: String,
+    pub maintainer: String,
+    pub source: String,
+    pub section: String,
+    pub priority: String,
+    pub homepage: String,
+    pub description: String,
+    /// `Installed-Size`, in KiB.
+    pub installed_size: Option<u64>,
+    /// Each comma-separated group, which may contain `|` alternatives and version constraints.
+    pub depends: Vec<String>,
+    pub pre_depends: Vec<String>,
+    pub recommends: Vec<String>,
+    pub suggests: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub breaks: Vec<String>,
+    pub replaces: Vec<String>,
+    pub provides: Vec<String>,
+    /// Every field, in file order, including those we don't otherwise understand.
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Md5Sum {
+    /// Lower-case hex.
+    pub md5: String,
+    /// Relative to the root of `data.tar`, without any leading `./`.
+    pub path: String,
+}
+
+/// Find and parse the control files inside a `.deb`'s `control.tar.*`.
+pub fn identify(children: &[Entry]) -> Option<DebInfo> {
+    package::child(children, b"debian-binary")?;
+    let control = children
+        .iter()
+        .find(|entry| entry.local.path.starts_with(b"control.tar"))?
+        .children
+        .entries();
+
+    Some(DebInfo {
+        control: parse_control(package::read_child(control, b"control")?),
+        md5sums: package::read_child(control, b"md5sums")
+            .map(parse_md5sums)
+            .unwrap_or_default(),
+        conffiles: package::read_child(control, b"conffiles")
+            .map(parse_conffiles)
+            .unwrap_or_default(),
+    })
+}
+
+/// Check a `.deb`'s `md5sums` against what was unpacked from its `data.tar`.
+/// Conffiles aren't reported as extra, as `dh_md5sums` leaves them out.
+/// Files which were themselves unpacked, e.g. gzipped man pages, aren't kept, so they're
+/// only checked if `Options::digests` was set; otherwise they're reported as unchecked.
+/// `None` if the entry isn't a deb, or the deb has no `md5sums`.
+pub fn verify_md5sums(deb: &Entry) -> Option<Verification> {
+    let info = match deb.package {
+        Some(Package::Deb(ref info)) if !info.md5sums.is_empty() => info,
+        _ => return None,
+    };
+
+    let data = deb
+        .children
+        .entries()
+        .iter()
+        .find(|entry| entry.local.path.starts_with(b"data.tar"))?
+        .children
+        .entries();
+
+    let mut files = package::regular_files(data);
+
+    let conffiles: HashSet<&str> = info
+        .conffiles
+        .iter()
+        .map(|path| path.trim_start_matches('/'))
+        .collect();
+
+    let mut report = Verification::default();
+
+    for sum in &info.md5sums {
+        let entry = match files.remove(&sum.path) {
+            Some(entry) => entry,
+            None => {
+                report.missing.push(sum.path.clone());
+                continue;
+            }
+        };
+
+        match package::content_digests(entry) {
+            Some(ref digests) if digest::hex(&digests.md5) == sum.md5 => (),
+            Some(_) => report.mismatched.push(sum.path.clone()),
+            None => report.unchecked.push(sum.path.clone()),
+        }
+    }
+
+    report.extra = files
+        .into_keys()
+        .filter(|path| !conffiles.contains(path.as_str()))
+        .collect();
+    report.extra.sort();
+
+    Some(report)
+}
+
+pub fn parse_control<R: Read>(from: R) -> DebControl {
+    let mut control = DebControl::default();
+
+    let fields = match paragraphs(from).into_iter().next() {
+        Some(fields) => fields,
+        None => return control,
+    };
+
+    for (key, value) in &fields {
+        let value = value.clone();
+        match key.to_ascii_lowercase().as_str() {
+            "package" => control.package = value,
+            "version" => control.version = value,
+            "architecture" => control.architecture = value,
+            "maintainer" => control.maintainer = value,
+            "source" => control.source = value,
+            "section" => control.section = value,
+            "priority" => control.priority = value,
+            "homepage" => control.homepage = value,
+            "description" => control.description = value,
+            "installed-size" => control.installed_size = value.trim().parse().ok(),
+            "depends" => control.depends = relationships(&value),
+            "pre-depends" => control.pre_depends = relationships(&value),
+            "recommends" => control.recommends = relationships(&value),
+            "suggests" => control.suggests = relationships(&value),
+            "conflicts" => control.conflicts = relationships(&value),
+            "breaks" => control.breaks = relationships(&value),
+            "replaces" => control.replaces = relationships(&value),
+            "provides" => control.provides = relationships(&value),
+            _ => (),
+        }
+    }
+
+    control.fields = fields;
+    control
+}
+
+pub fn parse_md5sums<R: Read>(from: R) -> Vec<Md5Sum> {
+    lines(from)
+        .iter()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            let md5 = parts.next()?.to_ascii_lowercase();
+            let path = parts.next()?.trim_start();
+            if md5.is_empty() || path.is_empty() {
+                return None;
+            }
+            Some(Md5Sum {
+                md5,
+                path: path.trim_start_matches("./").to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn parse_conffiles<R: Read>(from: R) -> Vec<String> {
+    lines(from)
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// The comma-separated groups of a relationship field, e.g. `Depends`.
+fn relationships(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|rel| rel.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|rel| !rel.is_empty())
+        .collect()
+}
+
+pub fn lines<R: Read>(mut from: R) -> Vec<String> {
+    let mut text = Vec::new();
+    if from.read_to_end(&mut text).is_err() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&text)
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Parse "deb822" style `Key: value` paragraphs, as used by `control` files and `.dsc`s.
+/// Continuation lines are joined with a newline, with their leading space removed.
+/// An inline PGP signature's armour is skipped.
+pub fn paragraphs<R: Read>(from: R) -> Vec<Vec<(String, String)>> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<(String, String)> = Vec::new();
+    let mut in_signature = false;
+    let mut in_armour_headers = false;
+
+    for line in lines(from) {
+        if line.starts_with("-----BEGIN PGP SIGNED MESSAGE") {
+            in_armour_headers = true;
+            continue;
+        }
+        if in_armour_headers {
+            in_armour_headers = !line.trim().is_empty();
+            continue;
+        }
+        if line.starts_with("-----BEGIN PGP SIGNATURE") {
+            in_signature = true;
+        }
+        if in_signature {
+            if line.starts_with("-----END PGP SIGNATURE") {
+                in_signature = false;
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(&mut (_, ref mut value)) = current.last_mut() {
+                value.push('\n');
+                value.push_str(&line[1..]);
+            }
+            continue;
+        }
+
+        if let Some(colon) = line.find(':') {
+            current.push((
+                line[..colon].to_string(),
+                line[colon + 1..].trim().to_string(),
+            ));
+        }
+    }
+
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+
+    paragraphs
+}
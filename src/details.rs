@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::executable;
+use crate::executable::Executable;
+use crate::file_type::FileType;
+
+/// Things learnt from a file's own content, rather than from what it unpacked to.
+#[derive(Clone, Debug)]
+pub enum Details {
+    Ar(ArIndex),
+    JavaClass(ClassVersion),
+    /// ELF, PE, Mach-O or wasm.
+    Executable(Box<Executable>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArFlavour {
+    /// Only short names, no symbol table.
+    Common,
+    /// Long names in a `//` member, symbols in `/`.
+    Gnu,
+    /// Long names inline as `#1/len`, symbols in `__.SYMDEF`.
+    Bsd,
+}
+
+/// The symbol index of a static library, which isn't presented as an entry.
+#[derive(Clone, Debug)]
+pub struct ArIndex {
+    pub flavour: ArFlavour,
+    pub symbols: Vec<Box<[u8]>>,
+}
+
+/// The version from a class file's header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClassVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ClassVersion {
+    /// The Java release which introduced this version, e.g. `1.4`, `8`, `17`.
+    pub fn java_release(&self) -> String {
+        match self.major {
+            45 => "1.1".to_string(),
+            46..=48 => format!("1.{}", self.major - 44),
+            major => format!("{}", major.saturating_sub(44)),
+        }
+    }
+}
+
+pub fn of<P: AsRef<Path>>(file_type: FileType, path: P) -> Option<Details> {
+    match file_type {
+        FileType::Ar => ar_index(path).map(Details::Ar),
+        FileType::JavaClass => class_version(path).map(Details::JavaClass),
+        FileType::Elf | FileType::Pe | FileType::MachO | FileType::Wasm => {
+            executable::of(file_type, path)
+                .map(|executable| Details::Executable(Box::new(executable)))
+        }
+        _ => None,
+    }
+}
+
+fn ar_index<P: AsRef<Path>>(path: P) -> Option<ArIndex> {
+    let mut archive = ar::Archive::new(fs::File::open(path).ok()?);
+    let symbols = archive
+        .symbols()
+        .ok()?
+        .map(|symbol| symbol.to_vec().into_boxed_slice())
+        .collect();
+
+    Some(ArIndex {
+        flavour: match archive.variant() {
+            ar::Variant::Common => ArFlavour::Common,
+            ar::Variant::GNU => ArFlavour::Gnu,
+            ar::Variant::BSD => ArFlavour::Bsd,
+        },
+        symbols,
+    })
+}
+
+fn class_version<P: AsRef<Path>>(path: P) -> Option<ClassVersion> {
+    let mut header = [0u8; 8];
+    fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+    Some(ClassVersion {
+        minor: u16::from_be_bytes([header[4], header[5]]),
+        major: u16::from_be_bytes([header[6], header[7]]),
+    })
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use md5::Digest;
+
+/// Hashes of an item's content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Digests {
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+    pub sha256: [u8; 32],
+}
+
+impl Digests {
+    pub fn of<R: Read>(mut from: R) -> io::Result<Digests> {
+        let mut md5 = md5::Md5::new();
+        let mut sha1 = sha1::Sha1::new();
+        let mut sha256 = sha2::Sha256::new();
+
+        let mut buf = [0u8; 8 * 1024];
+        loop {
+            let found = from.read(&mut buf)?;
+            if 0 == found {
+                break;
+            }
+            md5.update(&buf[..found]);
+            sha1.update(&buf[..found]);
+            sha256.update(&buf[..found]);
+        }
+
+        Ok(Digests {
+            md5: md5.finalize().into(),
+            sha1: sha1.finalize().into(),
+            sha256: sha256.finalize().into(),
+        })
+    }
+
+    pub fn of_path<P: AsRef<Path>>(path: P) -> io::Result<Digests> {
+        Digests::of(fs::File::open(path)?)
+    }
+
+    pub fn empty() -> Digests {
+        Digests::of(io::empty()).expect("reading nothing")
+    }
+}
+
+/// Lower-case hex, as used by `md5sum` and friends.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The URL-safe base64 alphabet, without padding, as used in Python's `RECORD` files.
+pub fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    let mut out = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]));
+        }
+    }
+    out
+}
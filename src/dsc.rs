@@ -0,0 +1,289 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::deb;
+use crate::digest;
+use crate::digest::Digests;
+use crate::mio::Mio;
+use crate::options::Options;
+use crate::temps::Temps;
+use crate::unpacker;
+use crate::unpacker::Entry;
+use crate::unpacker::Status;
+
+/// A Debian source control file.
+#[derive(Clone, Debug, Default)]
+pub struct Dsc {
+    pub source: String,
+    pub version: String,
+    /// e.g. `3.0 (quilt)`
+    pub format: String,
+    pub files: Vec<DscFile>,
+}
+
+/// A file listed in a `.dsc`, with whichever checksums it gave for it.
+#[derive(Clone, Debug, Default)]
+pub struct DscFile {
+    pub name: String,
+    pub size: u64,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+pub fn parse<R: Read>(from: R) -> Dsc {
+    let mut dsc = Dsc::default();
+
+    let fields = match deb::paragraphs(from).into_iter().next() {
+        Some(fields) => fields,
+        None => return dsc,
+    };
+
+    for (key, value) in fields {
+        match key.to_ascii_lowercase().as_str() {
+            "source" => dsc.source = value,
+            "version" => dsc.version = value,
+            "format" => dsc.format = value,
+            "files" => add_checksums(&mut dsc.files, &value, |file, sum| file.md5 = Some(sum)),
+            "checksums-sha1" => {
+                add_checksums(&mut dsc.files, &value, |file, sum| file.sha1 = Some(sum))
+            }
+            "checksums-sha256" => {
+                add_checksums(&mut dsc.files, &value, |file, sum| file.sha256 = Some(sum))
+            }
+            _ => (),
+        }
+    }
+
+    dsc
+}
+
+/// Each line of a checksum field is `checksum size name`.
+fn add_checksums<F>(files: &mut Vec<DscFile>, value: &str, mut set: F)
+where
+    F: FnMut(&mut DscFile, String),
+{
+    for line in value.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if 3 != parts.len() {
+            continue;
+        }
+
+        let index = match files.iter().position(|file| file.name == parts[2]) {
+            Some(index) => index,
+            None => {
+                files.push(DscFile {
+                    name: parts[2].to_string(),
+                    size: parts[1].parse().unwrap_or(0),
+                    ..DscFile::default()
+                });
+                files.len() - 1
+            }
+        };
+
+        set(&mut files[index], parts[0].to_ascii_lowercase());
+    }
+}
+
+/// The files a `.dsc` refers to, which are expected next to it.
+pub fn referenced<P: AsRef<Path>>(dsc_path: P) -> Vec<PathBuf> {
+    let dir = dsc_path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    match fs::File::open(dsc_path.as_ref()) {
+        Ok(file) => parse(file)
+            .files
+            .into_iter()
+            .filter(|file| is_plain_name(&file.name))
+            .map(|file| dir.join(file.name))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Check the listed files, then lay them out as `dpkg-source -x` would: the orig tarball at
+/// the root, `orig-COMPONENT` tarballs in `COMPONENT/`, and `debian/` replaced by the
+/// `.debian.tar`. Patches, and format 1.0 `.diff.gz`s, are not applied.
+pub fn assemble<P: AsRef<Path>>(dsc_path: P, temps: &mut Temps, options: &Options) -> Status {
+    let dsc_path = dsc_path.as_ref();
+    let dir = dsc_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let dsc = match fs::File::open(dsc_path) {
+        Ok(file) => parse(file),
+        Err(e) => return Status::Error(format!("reading {:?}: {}", dsc_path, e)),
+    };
+
+    if dsc.files.is_empty() {
+        return Status::Error(format!("no files listed in {:?}", dsc_path));
+    }
+
+    // the names are untrusted, so must stay next to the `.dsc`, as `dpkg-source` insists
+    if let Some(file) = dsc.files.iter().find(|file| !is_plain_name(&file.name)) {
+        return Status::Error(format!("bad file name in {:?}: {:?}", dsc_path, file.name));
+    }
+
+    for file in &dsc.files {
+        if let Err(e) = verify(file, &dir.join(&file.name)) {
+            return Status::Error(e);
+        }
+    }
+
+    let prefix = format!("{}_{}", dsc.source, upstream_version(&dsc.version));
+    let mut root = Vec::new();
+    let mut components = Vec::new();
+    let mut debian = Vec::new();
+    let mut problems = Vec::new();
+
+    for file in &dsc.files {
+        let name = file.name.as_str();
+        if name.ends_with(".asc") {
+            continue;
+        }
+
+        let target = if name.contains(".debian.tar.") {
+            &mut debian
+        } else if name.starts_with(&format!("{}.orig-", prefix)) {
+            let component = &name[prefix.len() + ".orig-".len()..];
+            let component = &component[..component.find(".tar.").unwrap_or(component.len())];
+            components.push((component.to_string(), Vec::new()));
+            &mut components.last_mut().expect("just pushed").1
+        } else if name.contains(".tar.") {
+            &mut root
+        } else {
+            problems.push(format!("{}: not applied", name));
+            continue;
+        };
+
+        match unpacker::unpack_unknown(
+            Mio::from_path(dir.join(name)).expect("verified"),
+            temps,
+            options,
+            1,
+        ) {
+            Status::Success(entries) => target.extend(entries),
+            Status::Partial(entries, e) => {
+                target.extend(entries);
+                problems.push(format!("{}: {}", name, e));
+            }
+            other => problems.push(format!("{}: {:?}", name, other)),
+        }
+    }
+
+    let mut tree: Vec<Entry> = strip_top_dir(root)
+        .into_iter()
+        .filter(|entry| debian.is_empty() || !in_dir(&entry.local.path, b"debian"))
+        .collect();
+
+    for (component, entries) in components {
+        for mut entry in strip_top_dir(entries) {
+            let mut path = component.as_bytes().to_vec();
+            path.push(b'/');
+            path.extend_from_slice(&entry.local.path);
+            entry.local.path = path.into_boxed_slice();
+            tree.push(entry);
+        }
+    }
+
+    for mut entry in debian {
+        entry.local.path = without_dot_slash(&entry.local.path).into();
+        tree.push(entry);
+    }
+
+    if problems.is_empty() {
+        Status::Success(tree)
+    } else {
+        Status::Partial(tree, problems.join("; "))
+    }
+}
+
+fn verify(file: &DscFile, path: &Path) -> Result<(), String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("reading {:?}: {}", path, e))?
+        .len();
+    if size != file.size {
+        return Err(format!(
+            "size mismatch for {}: expected {}, found {}",
+            file.name, file.size, size
+        ));
+    }
+
+    let digests = Digests::of_path(path).map_err(|e| format!("reading {:?}: {}", path, e))?;
+    let expected = [
+        (&file.md5, &digests.md5[..]),
+        (&file.sha1, &digests.sha1[..]),
+        (&file.sha256, &digests.sha256[..]),
+    ];
+
+    for (expected, actual) in &expected {
+        if let Some(expected) = expected {
+            if *expected != digest::hex(actual) {
+                return Err(format!("checksum mismatch for {}", file.name));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A name in the `.dsc`'s own directory: not a path anywhere else.
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains('/')
+        && "." != name
+        && ".." != name
+        && !Path::new(name).is_absolute()
+}
+
+/// Strip the epoch and Debian revision.
+fn upstream_version(version: &str) -> &str {
+    let version = &version[version.find(':').map_or(0, |colon| colon + 1)..];
+    &version[..version.rfind('-').unwrap_or(version.len())]
+}
+
+/// Remove the single top-level directory every entry is in, if there is one, like `dpkg-source`.
+fn strip_top_dir(entries: Vec<Entry>) -> Vec<Entry> {
+    let top = match entries.first() {
+        Some(entry) => top_dir(&entry.local.path).to_vec(),
+        None => return entries,
+    };
+
+    if top.is_empty()
+        || !entries
+            .iter()
+            .all(|entry| top_dir(&entry.local.path) == &top[..])
+    {
+        return entries;
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|mut entry| {
+            let path = without_dot_slash(&entry.local.path)[top.len()..]
+                .strip_prefix(b"/")
+                .unwrap_or(&[])
+                .to_vec();
+            if path.is_empty() {
+                return None;
+            }
+            entry.local.path = path.into_boxed_slice();
+            Some(entry)
+        })
+        .collect()
+}
+
+fn top_dir(path: &[u8]) -> &[u8] {
+    let path = without_dot_slash(path);
+    match path.iter().position(|&c| b'/' == c) {
+        Some(slash) => &path[..slash],
+        None => &[],
+    }
+}
+
+fn in_dir(path: &[u8], dir: &[u8]) -> bool {
+    let path = without_dot_slash(path);
+    path.starts_with(dir) && (path.len() == dir.len() || b'/' == path[dir.len()])
+}
+
+fn without_dot_slash(path: &[u8]) -> &[u8] {
+    path.strip_prefix(b"./").unwrap_or(path)
+}
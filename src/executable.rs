@@ -0,0 +1,357 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use goblin::elf;
+use goblin::mach;
+use goblin::pe;
+
+use crate::details::Details;
+use crate::digest;
+use crate::file_type::FileType;
+use crate::unpacker::Entry;
+
+/// The headline facts about a compiled binary.
+#[derive(Clone, Debug, Default)]
+pub struct Executable {
+    /// e.g. `x86_64`, `aarch64`, `wasm32`; empty for a fat Mach-O.
+    pub architecture: String,
+    pub bits: Option<u8>,
+    pub endianness: Option<Endianness>,
+    pub kind: ExecutableKind,
+    /// The dynamic loader requested, e.g. `/lib64/ld-linux-x86-64.so.2`.
+    pub interpreter: Option<String>,
+    /// The name a shared library is linked against: `DT_SONAME`, a dylib's install name,
+    /// or a DLL's export name.
+    pub soname: Option<String>,
+    /// Lower-case hex: the GNU build-id, Mach-O `LC_UUID`, or PE CodeView GUID and age.
+    pub build_id: Option<String>,
+    /// For a fat Mach-O, each architecture's binary.
+    pub slices: Vec<Executable>,
+    /// Dynamic linking and hardening, for ELF.
+    pub elf: Option<ElfDynamic>,
+}
+
+/// What an ELF needs from, and offers to, the dynamic linker.
+#[derive(Clone, Debug, Default)]
+pub struct ElfDynamic {
+    /// `DT_NEEDED`, in order.
+    pub needed: Vec<String>,
+    pub rpath: Vec<String>,
+    pub runpath: Vec<String>,
+    /// Defined, visible, global or weak dynamic symbols.
+    pub exports: Vec<String>,
+    /// Undefined dynamic symbols.
+    pub imports: Vec<String>,
+    pub hardening: Hardening,
+}
+
+/// The usual `checksec` questions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hardening {
+    /// Position-independent executable; always false for libraries.
+    pub pie: bool,
+    pub relro: Relro,
+    /// The stack isn't executable: `PT_GNU_STACK` without `PF_X`.
+    pub nx: bool,
+    /// Imports `__stack_chk_fail`.
+    pub stack_canary: bool,
+    /// Imports any of glibc's `_FORTIFY_SOURCE` `__*_chk` functions.
+    pub fortify: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Relro {
+    #[default]
+    None,
+    /// `PT_GNU_RELRO`, but lazy binding leaves the GOT writable.
+    Partial,
+    /// `PT_GNU_RELRO` and `BIND_NOW`.
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExecutableKind {
+    /// Including position-independent executables.
+    Executable,
+    SharedLibrary,
+    /// An object file, before linking.
+    Relocatable,
+    Core,
+    /// Including fat Mach-Os and wasm modules.
+    #[default]
+    Other,
+}
+
+pub fn of<P: AsRef<Path>>(file_type: FileType, path: P) -> Option<Executable> {
+    let data = fs::read(path).ok()?;
+    match file_type {
+        FileType::Elf => Some(of_elf(&elf::Elf::parse(&data).ok()?, &data)),
+        FileType::Pe => Some(of_pe(&pe::PE::parse(&data).ok()?)),
+        FileType::MachO => match mach::Mach::parse(&data).ok()? {
+            mach::Mach::Binary(macho) => Some(of_macho(&macho, &data)),
+            mach::Mach::Fat(fat) => Some(Executable {
+                slices: fat
+                    .arches()
+                    .ok()?
+                    .iter()
+                    .filter_map(|arch| {
+                        // a slice may also be a static library, which won't parse
+                        let data = arch.slice(&data);
+                        Some(of_macho(&mach::MachO::parse(data, 0).ok()?, data))
+                    })
+                    .collect(),
+                ..Executable::default()
+            }),
+        },
+        FileType::Wasm => Some(Executable {
+            architecture: "wasm32".to_string(),
+            bits: Some(32),
+            endianness: Some(Endianness::Little),
+            ..Executable::default()
+        }),
+        _ => None,
+    }
+}
+
+fn of_elf(binary: &elf::Elf, data: &[u8]) -> Executable {
+    use goblin::elf::header::*;
+
+    let kind = match binary.header.e_type {
+        ET_REL => ExecutableKind::Relocatable,
+        ET_EXEC => ExecutableKind::Executable,
+        // a PIE asks for a loader; a library doesn't
+        ET_DYN if binary.interpreter.is_some() => ExecutableKind::Executable,
+        ET_DYN => ExecutableKind::SharedLibrary,
+        ET_CORE => ExecutableKind::Core,
+        _ => ExecutableKind::Other,
+    };
+
+    let build_id = binary.iter_note_headers(data).and_then(|mut notes| {
+        notes.find_map(|note| match note {
+            Ok(ref note) if "GNU" == note.name && elf::note::NT_GNU_BUILD_ID == note.n_type => {
+                Some(digest::hex(note.desc))
+            }
+            _ => None,
+        })
+    });
+
+    Executable {
+        architecture: match binary.header.e_machine {
+            EM_386 => "x86".to_string(),
+            EM_X86_64 => "x86_64".to_string(),
+            EM_ARM => "arm".to_string(),
+            EM_AARCH64 => "aarch64".to_string(),
+            EM_RISCV => "riscv".to_string(),
+            EM_PPC => "powerpc".to_string(),
+            EM_PPC64 => "powerpc64".to_string(),
+            EM_MIPS => "mips".to_string(),
+            EM_S390 => "s390".to_string(),
+            EM_SPARCV9 => "sparc64".to_string(),
+            other => elf::header::machine_to_str(other).to_ascii_lowercase(),
+        },
+        bits: Some(if binary.is_64 { 64 } else { 32 }),
+        endianness: Some(if binary.little_endian {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }),
+        kind,
+        interpreter: binary.interpreter.map(|s| s.to_string()),
+        soname: binary.soname.map(|s| s.to_string()),
+        build_id,
+        slices: Vec::new(),
+        elf: Some(elf_dynamic(binary, kind)),
+    }
+}
+
+fn elf_dynamic(binary: &elf::Elf, kind: ExecutableKind) -> ElfDynamic {
+    use goblin::elf::dynamic::*;
+    use goblin::elf::program_header::*;
+    use goblin::elf::section_header::SHN_UNDEF;
+    use goblin::elf::sym::*;
+
+    let mut exports = Vec::new();
+    let mut imports = Vec::new();
+    for sym in binary.dynsyms.iter() {
+        let bind = st_bind(sym.st_info);
+        if STB_GLOBAL != bind && STB_WEAK != bind {
+            continue;
+        }
+        let name = match binary.dynstrtab.get_at(sym.st_name) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => continue,
+        };
+
+        if SHN_UNDEF == sym.st_shndx as u32 {
+            imports.push(name);
+        } else if STV_HIDDEN != st_visibility(sym.st_other)
+            && STV_INTERNAL != st_visibility(sym.st_other)
+        {
+            exports.push(name);
+        }
+    }
+
+    let (flags, flags_1, bind_now) = match binary.dynamic {
+        Some(ref dynamic) => (
+            dynamic.info.flags,
+            dynamic.info.flags_1,
+            dynamic.dyns.iter().any(|dyn_| DT_BIND_NOW == dyn_.d_tag),
+        ),
+        None => (0, 0, false),
+    };
+
+    let has_segment = |kind| binary.program_headers.iter().any(|ph| kind == ph.p_type);
+    let relro = if !has_segment(PT_GNU_RELRO) {
+        Relro::None
+    } else if bind_now || 0 != flags & DF_BIND_NOW || 0 != flags_1 & DF_1_NOW {
+        Relro::Full
+    } else {
+        Relro::Partial
+    };
+
+    let hardening = Hardening {
+        pie: elf::header::ET_DYN == binary.header.e_type
+            && (ExecutableKind::Executable == kind || 0 != flags_1 & DF_1_PIE),
+        relro,
+        nx: binary
+            .program_headers
+            .iter()
+            .any(|ph| PT_GNU_STACK == ph.p_type && 0 == ph.p_flags & PF_X),
+        stack_canary: imports.iter().any(|name| "__stack_chk_fail" == name),
+        fortify: imports.iter().any(|name| {
+            name.starts_with("__") && name.ends_with("_chk") && name != "__stack_chk_fail"
+        }),
+    };
+
+    let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+
+    ElfDynamic {
+        needed: strings(&binary.libraries),
+        rpath: strings(&binary.rpaths),
+        runpath: strings(&binary.runpaths),
+        exports,
+        imports,
+        hardening,
+    }
+}
+
+/// Every library needed by an ELF at or below these entries, e.g. a `.deb`'s children,
+/// except those provided, by `DT_SONAME`, by a library alongside them. Sorted.
+pub fn required_libraries(entries: &[Entry]) -> Vec<String> {
+    let mut needed = BTreeSet::new();
+    let mut provided = BTreeSet::new();
+    collect_libraries(entries, &mut needed, &mut provided);
+    needed.difference(&provided).cloned().collect()
+}
+
+fn collect_libraries(
+    entries: &[Entry],
+    needed: &mut BTreeSet<String>,
+    provided: &mut BTreeSet<String>,
+) {
+    for entry in entries {
+        if let Some(Details::Executable(ref executable)) = entry.details {
+            if let Some(ref elf) = executable.elf {
+                needed.extend(elf.needed.iter().cloned());
+            }
+            if let Some(ref soname) = executable.soname {
+                provided.insert(soname.clone());
+            }
+        }
+        collect_libraries(entry.children.entries(), needed, provided);
+    }
+}
+
+fn of_pe(binary: &pe::PE) -> Executable {
+    use goblin::pe::header::*;
+
+    let build_id = binary
+        .debug_data
+        .as_ref()
+        .and_then(|debug| debug.codeview_pdb70_debug_info.as_ref())
+        .map(|codeview| format!("{}{:x}", digest::hex(&codeview.signature), codeview.age));
+
+    Executable {
+        architecture: match binary.header.coff_header.machine {
+            COFF_MACHINE_X86 => "x86".to_string(),
+            COFF_MACHINE_X86_64 => "x86_64".to_string(),
+            COFF_MACHINE_ARM | COFF_MACHINE_ARMNT => "arm".to_string(),
+            COFF_MACHINE_ARM64 => "aarch64".to_string(),
+            other => pe::header::machine_to_str(other).to_ascii_lowercase(),
+        },
+        bits: Some(if binary.is_64 { 64 } else { 32 }),
+        endianness: Some(Endianness::Little),
+        kind: if binary.is_lib {
+            ExecutableKind::SharedLibrary
+        } else {
+            ExecutableKind::Executable
+        },
+        interpreter: None,
+        soname: binary.name.map(|s| s.to_string()),
+        build_id,
+        slices: Vec::new(),
+        elf: None,
+    }
+}
+
+fn of_macho(binary: &mach::MachO, data: &[u8]) -> Executable {
+    use goblin::mach::constants::cputype::*;
+    use goblin::mach::header::*;
+    use goblin::mach::load_command::CommandVariant;
+
+    let mut interpreter = None;
+    let mut build_id = None;
+    for command in &binary.load_commands {
+        match command.command {
+            CommandVariant::Uuid(ref uuid) => build_id = Some(digest::hex(&uuid.uuid)),
+            CommandVariant::LoadDylinker(ref dylinker) => {
+                // the name is a NUL-terminated string inside the command
+                interpreter = c_string_at(data, command.offset + dylinker.name as usize);
+            }
+            _ => (),
+        }
+    }
+
+    Executable {
+        architecture: match binary.header.cputype() {
+            CPU_TYPE_X86 => "x86".to_string(),
+            CPU_TYPE_X86_64 => "x86_64".to_string(),
+            CPU_TYPE_ARM => "arm".to_string(),
+            CPU_TYPE_ARM64 | CPU_TYPE_ARM64_32 => "aarch64".to_string(),
+            CPU_TYPE_POWERPC => "powerpc".to_string(),
+            CPU_TYPE_POWERPC64 => "powerpc64".to_string(),
+            other => format!("cputype-{:x}", other),
+        },
+        bits: Some(if binary.is_64 { 64 } else { 32 }),
+        endianness: Some(if binary.little_endian {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }),
+        kind: match binary.header.filetype {
+            MH_OBJECT => ExecutableKind::Relocatable,
+            MH_EXECUTE => ExecutableKind::Executable,
+            MH_DYLIB | MH_BUNDLE => ExecutableKind::SharedLibrary,
+            MH_CORE => ExecutableKind::Core,
+            _ => ExecutableKind::Other,
+        },
+        interpreter,
+        soname: binary.name.map(|s| s.to_string()),
+        build_id,
+        slices: Vec::new(),
+        elf: None,
+    }
+}
+
+fn c_string_at(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|&b| 0 == b)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
@@ -1,15 +1,45 @@
-use std;
 use std::fmt;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum FileType {
     // Archives
     Gz,
-    Zip,
+    Zip(ZipKind),
     Tar,
     Bz,
     Xz,
     Deb,
+    /// Any other Unix archive, e.g. a static library.
+    Ar,
+    /// A raw deflate stream, which has no header: only ever suggested by a name.
+    Deflate,
+    /// Legacy "LZMA-alone", as written by LZMA Utils and `xz --format=lzma`.
+    Lzma,
+    Lzip,
+    /// Unix `compress`: LZW, usually `.Z`.
+    Compress,
+    Lzop,
+    /// An LZ4 frame; not the legacy format.
+    Lz4,
+    /// Brotli has no header: only ever suggested by a name.
+    Brotli,
+    /// A bare zlib stream, e.g. a git loose object; see `is_probably_zlib`.
+    Zlib,
+    /// A git packfile, on its own.
+    GitPack,
+    /// A CD or DVD image; its magic is at 32KiB, so is never seen by `FileType::identify`.
+    Iso9660,
+
+    // Executables
+    /// A compiled Java class; see `Details::JavaClass` for its version.
+    JavaClass,
+    Elf,
+    /// A Windows executable or DLL: an `MZ` stub pointing at a `PE` header.
+    Pe,
+    /// Including fat (universal) binaries.
+    MachO,
+    /// A WebAssembly module.
+    Wasm,
 
     // Special
     Empty,
@@ -18,6 +48,50 @@ pub enum FileType {
     Other,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ZipKind {
+    Plain,
+    /// A jar, war, ear, ...: a member in `META-INF/`, of those whose headers we see.
+    Java,
+}
+
+/// What `identify` found, and how sure it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identification {
+    pub file_type: FileType,
+    pub confidence: Confidence,
+    pub mime: &'static str,
+    pub evidence: Evidence,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// A guess, e.g. from the name alone, or the absence of anything better.
+    Low,
+    /// A heuristic, or a short magic number, matched.
+    Medium,
+    /// A distinctive magic number, or a checksum, matched.
+    High,
+    /// A long magic number matched; or there was nothing to identify.
+    Certain,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Evidence {
+    /// These bytes were found at this offset.
+    Magic { offset: usize, bytes: Vec<u8> },
+    /// A tar header with a valid checksum, but no `ustar` magic.
+    TarChecksum,
+    /// A zip end of central directory record in the tail, after something else.
+    ZipTrailer,
+    /// A heuristic over the content, described.
+    Content(&'static str),
+    /// The name's extension, as the content was inconclusive.
+    Extension(String),
+    /// The file was empty.
+    Nothing,
+}
+
 impl fmt::Display for FileType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(self, f)
@@ -61,15 +135,9 @@ pub fn is_probably_tar(header: &[u8]) -> bool {
     }
 
     if let Some(expected) = read_octal(&header[148..156]) {
-        let found: u32 = header[0..148]
-            .into_iter()
-            .map(|&x| u32::from(x))
-            .sum::<u32>()
+        let found: u32 = header[0..148].iter().map(|&x| u32::from(x)).sum::<u32>()
             + u32::from(b' ') * 8
-            + header[156..512]
-                .into_iter()
-                .map(|&x| u32::from(x))
-                .sum::<u32>();
+            + header[156..512].iter().map(|&x| u32::from(x)).sum::<u32>();
 
         if expected == found {
             return true;
@@ -79,10 +147,313 @@ pub fn is_probably_tar(header: &[u8]) -> bool {
     false
 }
 
+/// The end of central directory record is 22 bytes, followed by a comment of up to 64KiB.
+pub const ZIP_TRAILER_CAP: u64 = 22 + 0xffff;
+
+/// Look for a zip end of central directory record in the tail of a file, which finds
+/// archives with something prepended, e.g. self-extractors and `.jar` launcher scripts.
+pub fn has_zip_trailer(tail: &[u8]) -> bool {
+    if tail.len() < 22 {
+        return false;
+    }
+
+    (0..=tail.len() - 22).rev().any(|pos| {
+        tail[pos..pos + 4] == b"PK\x05\x06"[..]
+            && usize::from(u16::from_le_bytes([tail[pos + 20], tail[pos + 21]]))
+                == tail.len() - pos - 22
+    })
+}
+
+pub const ISO9660_MAGIC_OFFSET: u64 = crate::iso9660::MAGIC_OFFSET;
+
+/// Given the bytes from `ISO9660_MAGIC_OFFSET`: the first volume descriptor's identifier.
+pub fn is_iso9660(at_magic: &[u8]) -> bool {
+    at_magic.starts_with(b"CD001")
+}
+
+/// A zlib header is deflate, a window size and a check, which one in 31 random pairs of bytes
+/// pass, so the start of the stream must also inflate.
+pub fn is_probably_zlib(header: &[u8]) -> bool {
+    if header.len() < 3 {
+        return false;
+    }
+
+    // deflate, a window of at most 32KiB, and no preset dictionary
+    let (cmf, flg) = (header[0], header[1]);
+    if 8 != cmf & 0x0f || cmf >> 4 > 7 || 0 != flg & 0x20 {
+        return false;
+    }
+
+    if 0 != (u16::from(cmf) << 8 | u16::from(flg)) % 31 {
+        return false;
+    }
+
+    let mut inflater = flate2::Decompress::new(true);
+    let mut out = vec![0u8; 32 * 1024];
+    match inflater.decompress(header, &mut out, flate2::FlushDecompress::None) {
+        Ok(flate2::Status::StreamEnd) => true,
+        Ok(_) => inflater.total_out() > 0,
+        Err(_) => false,
+    }
+}
+
+/// Walk the members whose local headers are in `header`, looking for `META-INF/`.
+fn zip_kind(header: &[u8]) -> ZipKind {
+    // spanned marker
+    let mut offset = if b"PK\x03\x04" != &header[..4] { 4 } else { 0 };
+
+    while let Some(local) = header.get(offset..offset + 30) {
+        if b"PK\x03\x04" != &local[..4] {
+            break;
+        }
+
+        let flags = u16::from_le_bytes([local[6], local[7]]);
+        let compressed = u32::from_le_bytes([local[18], local[19], local[20], local[21]]);
+        let name_len = usize::from(u16::from_le_bytes([local[26], local[27]]));
+        let extra_len = usize::from(u16::from_le_bytes([local[28], local[29]]));
+
+        match header.get(offset + 30..offset + 30 + name_len) {
+            Some(name) if name.starts_with(b"META-INF/") => return ZipKind::Java,
+            Some(_) => (),
+            None => break,
+        }
+
+        // the size is only in the data descriptor, after the data
+        if 0 != flags & 0x08 {
+            break;
+        }
+
+        offset = offset
+            .saturating_add(30 + name_len + extra_len)
+            .saturating_add(compressed as usize);
+    }
+
+    ZipKind::Plain
+}
+
 const DEB_PREFIX: &[u8] = b"!<arch>\ndebian-binary ";
+const LZOP_MAGIC: &[u8] = b"\x89LZO\0\r\n\x1a\n";
+
+/// Extensions, lower-cased, and what they claim to be.
+const EXTENSIONS: &[(&str, FileType)] = &[
+    ("gz", FileType::Gz),
+    ("tgz", FileType::Gz),
+    ("zip", FileType::Zip(ZipKind::Plain)),
+    ("jar", FileType::Zip(ZipKind::Java)),
+    ("war", FileType::Zip(ZipKind::Java)),
+    ("ear", FileType::Zip(ZipKind::Java)),
+    ("tar", FileType::Tar),
+    ("bz2", FileType::Bz),
+    ("tbz2", FileType::Bz),
+    ("xz", FileType::Xz),
+    ("txz", FileType::Xz),
+    ("deb", FileType::Deb),
+    ("a", FileType::Ar),
+    ("class", FileType::JavaClass),
+    ("exe", FileType::Pe),
+    ("dll", FileType::Pe),
+    ("dylib", FileType::MachO),
+    ("wasm", FileType::Wasm),
+    ("deflate", FileType::Deflate),
+    ("lzma", FileType::Lzma),
+    ("lz", FileType::Lzip),
+    ("z", FileType::Compress),
+    ("taz", FileType::Compress),
+    ("lzo", FileType::Lzop),
+    ("tzo", FileType::Lzop),
+    ("lz4", FileType::Lz4),
+    ("br", FileType::Brotli),
+    ("iso", FileType::Iso9660),
+];
+
+/// The type an entry was unpacked as because of its name, as its content was inconclusive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeHint {
+    pub file_type: FileType,
+    /// Lower-cased, without the dot.
+    pub extension: String,
+}
+
+/// The type its extension claims for a name, if any. Dotfiles, and so the `..gz`-style names
+/// we give decoded streams, have no extension.
+pub fn by_extension(name: &[u8]) -> Option<(FileType, String)> {
+    let name = name.rsplit(|&b| b'/' == b).next()?;
+    let dot = name.iter().rposition(|&b| b'.' == b)?;
+    if name[..dot].iter().all(|&b| b'.' == b) {
+        return None;
+    }
+
+    let extension = String::from_utf8_lossy(&name[dot + 1..]).to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(known, _)| extension == *known)
+        .map(|&(_, file_type)| (file_type, extension))
+}
+
+/// Refine what `FileType::identify` made of a header by looking further into the file, which
+/// is only fetched if it's needed: `at_iso9660_magic` gives the bytes at `ISO9660_MAGIC_OFFSET`,
+/// and `tail` up to the last `ZIP_TRAILER_CAP` bytes, if they're available.
+pub fn identify_further<M, T>(
+    file_type: FileType,
+    header: &[u8],
+    at_iso9660_magic: M,
+    tail: T,
+    zlib_streams: bool,
+) -> FileType
+where
+    M: Fn() -> Vec<u8>,
+    T: Fn() -> Option<Vec<u8>>,
+{
+    match file_type {
+        FileType::Binary if is_iso9660(&at_iso9660_magic()) => FileType::Iso9660,
+        FileType::Binary | FileType::Source | FileType::Other
+            if tail().is_some_and(|tail| has_zip_trailer(&tail)) =>
+        {
+            FileType::Zip(ZipKind::Plain)
+        }
+        FileType::Binary | FileType::Other if zlib_streams && is_probably_zlib(header) => {
+            FileType::Zlib
+        }
+        other => other,
+    }
+}
+
+/// Identify a file from its first few KiB; optionally its last `ZIP_TRAILER_CAP` bytes, which
+/// find zips with something prepended; and optionally the name it was found under, which is
+/// only trusted when the content is inconclusive.
+pub fn identify(header: &[u8], tail: Option<&[u8]>, name: Option<&[u8]>) -> Identification {
+    let from_header = FileType::identify(header);
+    let file_type = identify_further(
+        from_header,
+        header,
+        || {
+            header
+                .get(ISO9660_MAGIC_OFFSET as usize..)
+                .unwrap_or(&[])
+                .to_vec()
+        },
+        || tail.map(<[u8]>::to_vec),
+        true,
+    );
+    let hinted = name.and_then(by_extension);
+
+    let (mut confidence, evidence) = match file_type {
+        FileType::Empty => (Confidence::Certain, Evidence::Nothing),
+        FileType::Gz => (Confidence::Medium, magic(header, 0, 2)),
+        FileType::Zip(_) if !matches!(from_header, FileType::Zip(_)) => {
+            (Confidence::Medium, Evidence::ZipTrailer)
+        }
+        FileType::Zip(_) => (Confidence::High, magic(header, 0, 4)),
+        FileType::Deb => (Confidence::Certain, magic(header, 0, DEB_PREFIX.len())),
+        FileType::Ar => (Confidence::High, magic(header, 0, 8)),
+        FileType::Bz => (Confidence::Certain, magic(header, 0, 10)),
+        FileType::Xz => (Confidence::Certain, magic(header, 0, 6)),
+        FileType::JavaClass => (Confidence::High, magic(header, 0, 4)),
+        FileType::Elf => (Confidence::High, magic(header, 0, 4)),
+        FileType::Pe => (
+            Confidence::High,
+            magic(header, pe_offset(header).unwrap_or(0), 4),
+        ),
+        FileType::MachO => (Confidence::High, magic(header, 0, 4)),
+        FileType::Wasm => (Confidence::High, magic(header, 0, 4)),
+        FileType::Tar if b"ustar" == &header[257..262] => (Confidence::High, magic(header, 257, 5)),
+        FileType::Tar => (Confidence::High, Evidence::TarChecksum),
+        FileType::Deflate | FileType::Brotli => unreachable!("{:?} has no magic", file_type),
+        FileType::Lzma => (
+            Confidence::Medium,
+            Evidence::Content("LZMA-alone header, which decodes"),
+        ),
+        FileType::Lzip => (Confidence::High, magic(header, 0, 5)),
+        FileType::Compress => (Confidence::Medium, magic(header, 0, 2)),
+        FileType::Lzop => (Confidence::Certain, magic(header, 0, LZOP_MAGIC.len())),
+        FileType::Lz4 => (Confidence::High, magic(header, 0, 4)),
+        FileType::GitPack => (Confidence::High, magic(header, 0, 8)),
+        FileType::Iso9660 => (
+            Confidence::High,
+            magic(header, ISO9660_MAGIC_OFFSET as usize, 5),
+        ),
+        FileType::Zlib => (
+            Confidence::Medium,
+            Evidence::Content("zlib header which inflates"),
+        ),
+        FileType::Binary | FileType::Source | FileType::Other => match hinted {
+            Some((hinted, extension)) => {
+                return Identification {
+                    file_type: hinted,
+                    confidence: Confidence::Low,
+                    mime: hinted.mime(),
+                    evidence: Evidence::Extension(extension),
+                };
+            }
+            None => match file_type {
+                FileType::Binary => (Confidence::Low, Evidence::Content("contains a NUL byte")),
+                FileType::Source => (
+                    Confidence::Medium,
+                    Evidence::Content("starts like source code"),
+                ),
+                _ => (Confidence::Low, Evidence::Content("no NUL bytes")),
+            },
+        },
+    };
+
+    if confidence < Confidence::High && hinted.is_some_and(|(hinted, _)| hinted == file_type) {
+        confidence = Confidence::High;
+    }
+
+    Identification {
+        file_type,
+        confidence,
+        mime: if FileType::Other == file_type && std::str::from_utf8(header).is_err() {
+            "application/octet-stream"
+        } else {
+            file_type.mime()
+        },
+        evidence,
+    }
+}
+
+fn magic(header: &[u8], offset: usize, len: usize) -> Evidence {
+    Evidence::Magic {
+        offset,
+        bytes: header[offset..offset + len].to_vec(),
+    }
+}
+
+impl FileType {
+    pub fn mime(&self) -> &'static str {
+        match *self {
+            FileType::Gz => "application/gzip",
+            FileType::Zip(ZipKind::Plain) => "application/zip",
+            FileType::Zip(ZipKind::Java) => "application/java-archive",
+            FileType::Tar => "application/x-tar",
+            FileType::Bz => "application/x-bzip2",
+            FileType::Xz => "application/x-xz",
+            FileType::Deb => "application/vnd.debian.binary-package",
+            FileType::Ar => "application/x-archive",
+            FileType::Deflate => "application/x-deflate",
+            FileType::Lzma => "application/x-lzma",
+            FileType::Lzip => "application/x-lzip",
+            FileType::Compress => "application/x-compress",
+            FileType::Lzop => "application/x-lzop",
+            FileType::Lz4 => "application/x-lz4",
+            FileType::Brotli => "application/x-brotli",
+            FileType::Zlib => "application/zlib",
+            FileType::GitPack => "application/x-git",
+            FileType::Iso9660 => "application/x-iso9660-image",
+            FileType::JavaClass => "application/java-vm",
+            FileType::Elf => "application/x-elf",
+            FileType::Pe => "application/vnd.microsoft.portable-executable",
+            FileType::MachO => "application/x-mach-binary",
+            FileType::Wasm => "application/wasm",
+            FileType::Empty => "application/x-empty",
+            FileType::Binary => "application/octet-stream",
+            FileType::Source | FileType::Other => "text/plain",
+        }
+    }
+}
 
 impl FileType {
-    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[rustfmt::skip]
     pub fn identify(header: &[u8]) -> FileType {
         if header.is_empty() {
             FileType::Empty
@@ -91,12 +462,18 @@ impl FileType {
             FileType::Gz
         } else if header.len() >= 152
             && b'P' == header[0] && b'K' == header[1]
-            && 0x03 == header[2] && 0x04 == header[3] {
-            FileType::Zip
+            && ((0x03 == header[2] && 0x04 == header[3])
+                // single-segment split/spanned marker, followed by a normal local header
+                || ((0x07 == header[2] && 0x08 == header[3]) || (b'0' == header[2] && b'0' == header[3]))
+                    && header[4..8] == b"PK\x03\x04"[..]) {
+            FileType::Zip(zip_kind(header))
         } else if header.len() > 70
             && header[0..DEB_PREFIX.len()] == DEB_PREFIX[..]
             && header[66..70] == b"`\n2."[..] {
             FileType::Deb
+        } else if header.len() > 8
+            && header.starts_with(b"!<arch>\n") {
+            FileType::Ar
         } else if header.len() > 40
             && b'B' == header[0] && b'Z' == header[1]
             && b'h' == header[2] // [3]: compression level
@@ -109,8 +486,45 @@ impl FileType {
             && b'z' == header[2] && b'X' == header[3]
             && b'Z' == header[4] && 0 == header[5] {
             FileType::Xz
+        } else if header.len() >= 10
+            && 0xca == header[0] && 0xfe == header[1]
+            && 0xba == header[2] && 0xbe == header[3]
+            // Mach-O fat binaries share the magic, but have a small architecture count here
+            && u32::from_be_bytes([header[4], header[5], header[6], header[7]]) >= 45 {
+            FileType::JavaClass
+        } else if header.len() >= 16
+            && header.starts_with(b"\x7fELF") {
+            FileType::Elf
+        } else if is_pe(header) {
+            FileType::Pe
+        } else if header.len() >= 8
+            && is_macho(header) {
+            FileType::MachO
+        } else if header.len() >= 8
+            && header.starts_with(b"\0asm") {
+            FileType::Wasm
+        } else if header.len() > 6
+            && header.starts_with(b"LZIP") && 1 == header[4] {
+            FileType::Lzip
+        } else if header.len() > 3
+            && 0x1f == header[0] && 0x9d == header[1]
+            // code width 9 to 16, and no reserved bits
+            && (9..=16).contains(&(header[2] & 0x1f)) && 0 == header[2] & 0x60 {
+            FileType::Compress
+        } else if header.len() > LZOP_MAGIC.len()
+            && header.starts_with(LZOP_MAGIC) {
+            FileType::Lzop
+        } else if header.len() > 7
+            && header.starts_with(b"\x04\x22\x4d\x18") {
+            FileType::Lz4
+        } else if header.len() > 12
+            && header.starts_with(b"PACK\0\0\0")
+            && (2 == header[7] || 3 == header[7]) {
+            FileType::GitPack
         } else if is_probably_tar(header) {
             FileType::Tar
+        } else if is_probably_lzma(header) {
+            FileType::Lzma
         } else if header.contains(&0) {
             FileType::Binary
         } else if source(header) {
@@ -121,6 +535,69 @@ impl FileType {
     }
 }
 
+/// LZMA-alone has no magic; its 13 byte header is the properties byte, a dictionary size
+/// (which encoders always make 2^n or 2^n + 2^(n-1)), and a length, often unknown (-1).
+/// Plenty of binaries start like that, so the start of the stream must also decode.
+fn is_probably_lzma(header: &[u8]) -> bool {
+    if header.len() < 13 || header[0] >= 9 * 5 * 5 {
+        return false;
+    }
+
+    let dictionary = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+    let length = u64::from_le_bytes([
+        header[5], header[6], header[7], header[8], header[9], header[10], header[11], header[12],
+    ]);
+
+    if dictionary < 4096 {
+        return false;
+    }
+
+    let shape = dictionary >> dictionary.trailing_zeros();
+    if !(1 == shape || 3 == shape) || !(u64::MAX == length || length < 1 << 48) {
+        return false;
+    }
+
+    // a dictionary beyond what any preset uses isn't worth allocating to find out
+    let mut decoder = match xz2::stream::Stream::new_lzma_decoder(256 * 1024 * 1024) {
+        Ok(decoder) => decoder,
+        Err(_) => return false,
+    };
+    let mut out = Vec::with_capacity(32 * 1024);
+    match decoder.process_vec(header, &mut out, xz2::stream::Action::Run) {
+        Ok(xz2::stream::Status::StreamEnd) => true,
+        Ok(_) => !out.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// The `MZ` stub's `e_lfanew` must point at a `PE` signature we can see.
+fn is_pe(header: &[u8]) -> bool {
+    if header.len() < 64 || !header.starts_with(b"MZ") {
+        return false;
+    }
+
+    pe_offset(header).is_some()
+}
+
+fn pe_offset(header: &[u8]) -> Option<usize> {
+    let offset = u32::from_le_bytes([header[60], header[61], header[62], header[63]]) as usize;
+    match header.get(offset..offset + 4) {
+        Some(b"PE\0\0") => Some(offset),
+        _ => None,
+    }
+}
+
+fn is_macho(header: &[u8]) -> bool {
+    match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+        0xfeed_face | 0xfeed_facf | 0xcefa_edfe | 0xcffa_edfe => true,
+        // fat, or fat with 64-bit offsets; a class file has its version here
+        0xcafe_babe | 0xcafe_babf => (1..45).contains(&u32::from_be_bytes([
+            header[4], header[5], header[6], header[7],
+        ])),
+        _ => false,
+    }
+}
+
 fn source(header: &[u8]) -> bool {
     // Unix script shebang with absolute path.
     if header.len() > 16
@@ -169,3 +646,118 @@ fn contains(haystack: &[u8], needle: &[u8]) -> bool {
     // we don't especially care about the performance here; it's just convenient
     ::twoway::find_bytes(haystack, needle).is_some()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A classic end of central directory record, with its fields all as Zip64 leaves them.
+    fn saturated_eocd(comment: &[u8]) -> Vec<u8> {
+        let mut record = b"PK\x05\x06".to_vec();
+        record.extend_from_slice(&[0; 4]);
+        record.extend_from_slice(&[0xff; 4]);
+        record.extend_from_slice(&[0xff; 8]);
+        record.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+        record.extend_from_slice(comment);
+        record
+    }
+
+    #[test]
+    fn zip64_trailer() {
+        let mut tail = b"MZ stub and some entries".to_vec();
+
+        // Zip64 end of central directory, with 2^32 + 1 entries
+        tail.extend_from_slice(b"PK\x06\x06");
+        tail.extend_from_slice(&44u64.to_le_bytes());
+        tail.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        tail.extend_from_slice(&0x1_0000_0001u64.to_le_bytes());
+        tail.extend_from_slice(&0x1_0000_0001u64.to_le_bytes());
+        tail.extend_from_slice(&0x2_0000_0000u64.to_le_bytes());
+        tail.extend_from_slice(&0x1_0000_0000u64.to_le_bytes());
+
+        // locator
+        tail.extend_from_slice(b"PK\x06\x07");
+        tail.extend_from_slice(&[0; 4]);
+        tail.extend_from_slice(&0x3_0000_0000u64.to_le_bytes());
+        tail.extend_from_slice(&1u32.to_le_bytes());
+
+        let mut with_comment = tail.clone();
+        with_comment.extend(saturated_eocd(b"PK\x05\x06 in the comment"));
+        assert!(has_zip_trailer(&with_comment));
+
+        tail.extend(saturated_eocd(b""));
+        assert!(has_zip_trailer(&tail));
+
+        // the comment length must reach exactly to the end
+        tail.push(0);
+        assert!(!has_zip_trailer(&tail));
+    }
+
+    #[test]
+    fn zip_trailer_evidence() {
+        let mut file = b"#!/bin/sh\nexec java -jar \"$0\"\n".to_vec();
+        file.extend(saturated_eocd(b""));
+
+        let without = identify(&file, None, None);
+        assert_eq!(FileType::Binary, without.file_type);
+
+        let with = identify(&file, Some(&file), None);
+        assert_eq!(FileType::Zip(ZipKind::Plain), with.file_type);
+        assert_eq!(Evidence::ZipTrailer, with.evidence);
+        assert_eq!("application/zip", with.mime);
+    }
+
+    #[test]
+    fn spanned_zip() {
+        for marker in &[&b"PK\x07\x08"[..], &b"PK00"[..]] {
+            let mut header = marker.to_vec();
+            header.extend_from_slice(b"PK\x03\x04");
+            header.resize(200, 0);
+            assert_eq!(FileType::Zip(ZipKind::Plain), FileType::identify(&header));
+
+            // a marker on its own isn't enough
+            header[4] = b'X';
+            assert_ne!(FileType::Zip(ZipKind::Plain), FileType::identify(&header));
+        }
+    }
+
+    fn lzma(data: &[u8]) -> Vec<u8> {
+        let options = xz2::stream::LzmaOptions::new_preset(6).unwrap();
+        let stream = xz2::stream::Stream::new_lzma_encoder(&options).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+        std::io::Write::write_all(&mut encoder, data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn lzma_needs_to_decode() {
+        let mut header = lzma(&b"some text, compressed with lzma\n".repeat(100));
+        header.truncate(1024);
+        assert_eq!(FileType::Lzma, FileType::identify(&header));
+
+        // no dictionary at all
+        let mut header = vec![0x5d, 0, 0, 0, 0];
+        header.extend_from_slice(&[0xff; 8]);
+        header.resize(200, 0x42);
+        assert_eq!(FileType::Binary, FileType::identify(&header));
+
+        // a plausible header, but then not the zero a stream starts with
+        header[3] = 1;
+        assert!(!is_probably_lzma(&header));
+        header[13] = 0;
+        for (i, byte) in header[14..].iter_mut().enumerate() {
+            *byte = (i * 151 % 251) as u8;
+        }
+        assert_eq!(FileType::Binary, FileType::identify(&header));
+    }
+
+    #[test]
+    fn extensions() {
+        let brotli = Some((FileType::Brotli, "br".to_string()));
+        assert_eq!(brotli, by_extension(b"docs/notes.txt.BR"));
+        assert_eq!(brotli, by_extension(b"..x/.br.br"));
+        assert_eq!(None, by_extension(b"..br"));
+        assert_eq!(None, by_extension(b"docs/.br"));
+        assert_eq!(None, by_extension(b"br"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::Read;
+
+use crate::deb;
+use crate::package;
+use crate::unpacker::Entry;
+
+/// What a `.gem`'s `metadata.gz` says.
+#[derive(Clone, Debug, Default)]
+pub struct GemInfo {
+    pub name: String,
+    pub version: String,
+    pub summary: String,
+    pub homepage: String,
+    pub licenses: Vec<String>,
+    pub authors: Vec<String>,
+    pub dependencies: Vec<GemDependency>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GemDependency {
+    pub name: String,
+    /// e.g. `>= 1.2, < 2`
+    pub requirement: String,
+    /// `false` for development dependencies.
+    pub runtime: bool,
+}
+
+pub fn identify(children: &[Entry]) -> Option<GemInfo> {
+    package::child(children, b"data.tar.gz")?;
+    let metadata = package::child(children, b"metadata.gz")?;
+    let yaml = metadata.children.entries().first()?.local.temp.as_ref()?;
+    Some(parse_metadata(std::fs::File::open(yaml).ok()?))
+}
+
+/// Parse the subset of the `Gem::Specification` YAML that we care about, without a YAML
+/// parser: it's always generated by the same code, and full of Ruby tags.
+pub fn parse_metadata<R: Read>(from: R) -> GemInfo {
+    let mut info = GemInfo::default();
+
+    // the top-level key we're inside, for multi-line values
+    let mut section = String::new();
+    let mut in_requirement = false;
+    let mut operator = String::new();
+
+    for line in deb::lines(from) {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+
+        if 0 == indent && !trimmed.starts_with('-') {
+            let (key, value) = key_value(trimmed);
+            section = key.to_string();
+            match key {
+                "name" => info.name = unquote(value),
+                "summary" => info.summary = unquote(value),
+                "homepage" => info.homepage = unquote(value),
+                _ => (),
+            }
+            continue;
+        }
+
+        match section.as_str() {
+            "version" if trimmed.starts_with("version:") => {
+                info.version = unquote(key_value(trimmed).1);
+            }
+            "licenses" | "authors" if 0 == indent => {
+                let value = unquote(trimmed.trim_start_matches('-').trim());
+                if "licenses" == section {
+                    info.licenses.push(value);
+                } else {
+                    info.authors.push(value);
+                }
+            }
+            "dependencies" => {
+                if 0 == indent {
+                    info.dependencies.push(GemDependency::default());
+                    in_requirement = false;
+                    continue;
+                }
+
+                let dependency = match info.dependencies.last_mut() {
+                    Some(dependency) => dependency,
+                    None => continue,
+                };
+
+                if 2 == indent {
+                    let (key, value) = key_value(trimmed);
+                    in_requirement = "requirement" == key;
+                    match key {
+                        "name" => dependency.name = unquote(value),
+                        "type" => dependency.runtime = ":runtime" == value,
+                        _ => (),
+                    }
+                } else if in_requirement && trimmed.starts_with("- - ") {
+                    operator = unquote(&trimmed[4..]);
+                } else if in_requirement && trimmed.starts_with("version:") {
+                    if !dependency.requirement.is_empty() {
+                        dependency.requirement.push_str(", ");
+                    }
+                    dependency.requirement.push_str(&operator);
+                    dependency.requirement.push(' ');
+                    dependency
+                        .requirement
+                        .push_str(&unquote(key_value(trimmed).1));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    info
+}
+
+fn key_value(line: &str) -> (&str, &str) {
+    match line.find(':') {
+        Some(colon) => (&line[..colon], line[colon + 1..].trim()),
+        None => (line, ""),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches(|c| '"' == c || '\'' == c).to_string()
+}
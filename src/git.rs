@@ -0,0 +1,693 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use failure::err_msg;
+use failure::Error;
+use sha1::Digest;
+
+use crate::digest;
+use crate::meta;
+use crate::mio::Mio;
+use crate::temps::Temps;
+use crate::unpacker::LocalEntry;
+
+type Id = [u8; 20];
+
+/// How to present a git repository, i.e. a `.git` directory or a bare repository.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitView {
+    /// As the files it's stored in: compressed objects, packs, refs, ...
+    Files,
+    /// The files in the tree of a ref, or of a commit id, e.g. `HEAD` or `v1.0`.
+    Tree(String),
+    /// Every blob, named by its id, whether or not any commit refers to it.
+    Blobs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+type Object = (Kind, Rc<Vec<u8>>);
+
+/// Git's own limit is 4095, but nobody packs chains anything like that long.
+const MAX_DELTA_DEPTH: usize = 1024;
+/// Delta bases are kept around, as they're usually the base of more than one object.
+const CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// `HEAD`, `objects/` and `refs/`: what `git` itself checks for.
+pub fn is_repository(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
+
+/// Loose objects and packs are read; alternates, and objects only present in them, aren't.
+pub fn unpack_repository(
+    git_dir: &Path,
+    view: &GitView,
+    temps: &mut Temps,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let store = Store::open(git_dir)?;
+    match *view {
+        GitView::Files => unreachable!("not unpacking repositories"),
+        GitView::Tree(ref name) => {
+            let id = resolve(git_dir, name)?
+                .ok_or_else(|| format_err!("no such ref or commit: {:?}", name))?;
+            let (tree, committed) = store.peel(&id)?;
+            store.walk_tree(&tree, b"", committed, temps, entries, 0)
+        }
+        GitView::Blobs => store.blobs(temps, entries),
+    }
+}
+
+/// A pack on its own has no refs, and maybe no index, so this is every blob in it.
+pub fn unpack_pack(
+    mut from: Mio,
+    temps: &mut Temps,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let mut data = Vec::new();
+    from.read_to_end(&mut data)?;
+    let store = Store::scan(data)?;
+    store.blobs(temps, entries)
+}
+
+struct Store {
+    objects: Option<PathBuf>,
+    packs: Vec<Pack>,
+    cache: RefCell<Cache>,
+}
+
+struct Pack {
+    data: Vec<u8>,
+    offsets: HashMap<Id, u64>,
+}
+
+#[derive(Default)]
+struct Cache {
+    objects: HashMap<(usize, u64), Object>,
+    bytes: usize,
+}
+
+impl Store {
+    fn open(git_dir: &Path) -> Result<Store, Error> {
+        let objects = git_dir.join("objects");
+        let mut packs = Vec::new();
+
+        let pack_dir = objects.join("pack");
+        if pack_dir.is_dir() {
+            let mut paths = fs::read_dir(&pack_dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.sort();
+
+            for path in paths {
+                if path.extension().is_none_or(|ext| "pack" != ext) {
+                    continue;
+                }
+
+                let data = fs::read(&path)?;
+                pack_header(&data)?;
+                let idx = path.with_extension("idx");
+                if idx.is_file() {
+                    let offsets = parse_idx(&fs::read(&idx)?)
+                        .map_err(|e| format_err!("reading {:?}: {}", idx, e))?;
+                    packs.push(Pack { data, offsets });
+                } else {
+                    let mut scanned = Store::scan(data)?;
+                    packs.push(scanned.packs.remove(0));
+                }
+            }
+        }
+
+        Ok(Store {
+            objects: Some(objects),
+            packs,
+            cache: RefCell::new(Cache::default()),
+        })
+    }
+
+    /// Index a pack by reading every object in it, which needs the base of any
+    /// `REF_DELTA` to be found first.
+    fn scan(data: Vec<u8>) -> Result<Store, Error> {
+        let count = pack_header(&data)?;
+        let mut pending = Vec::with_capacity(count.min(1 << 20));
+        let mut offset = 12;
+        for _ in 0..count {
+            pending.push(offset as u64);
+            let (kind, size, mut pos) = entry_header(&data, offset)?;
+            match kind {
+                6 => pos = ofs_delta(&data, pos)?.1,
+                7 => pos += 20,
+                _ => (),
+            }
+            let (_, used) = inflate(data.get(pos..).ok_or_else(truncated)?, size)?;
+            offset = pos + used;
+        }
+
+        let mut store = Store {
+            objects: None,
+            packs: vec![Pack {
+                data,
+                offsets: HashMap::new(),
+            }],
+            cache: RefCell::new(Cache::default()),
+        };
+
+        while !pending.is_empty() {
+            let mut deferred = Vec::new();
+            let mut failure = None;
+            for &offset in &pending {
+                match store.read_at(0, offset, 0) {
+                    Ok((kind, data)) => {
+                        store.packs[0].offsets.insert(id_of(kind, &data), offset);
+                    }
+                    Err(e) => {
+                        deferred.push(offset);
+                        failure = Some(e);
+                    }
+                }
+            }
+
+            if deferred.len() == pending.len() {
+                return Err(failure.expect("something failed"));
+            }
+            pending = deferred;
+        }
+
+        Ok(store)
+    }
+
+    fn read(&self, id: &Id, depth: usize) -> Result<Option<Object>, Error> {
+        for (index, pack) in self.packs.iter().enumerate() {
+            if let Some(&offset) = pack.offsets.get(id) {
+                return self.read_at(index, offset, depth).map(Some);
+            }
+        }
+
+        let objects = match self.objects {
+            Some(ref objects) => objects,
+            None => return Ok(None),
+        };
+
+        let hex = digest::hex(id);
+        let path = objects.join(&hex[..2]).join(&hex[2..]);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let mut loose = Vec::new();
+        flate2::read::ZlibDecoder::new(fs::File::open(&path)?).read_to_end(&mut loose)?;
+        let nul = loose
+            .iter()
+            .position(|&b| 0 == b)
+            .ok_or_else(|| format_err!("bad loose object header: {}", hex))?;
+        let kind = match loose[..nul].split(|&b| b' ' == b).next() {
+            Some(b"commit") => Kind::Commit,
+            Some(b"tree") => Kind::Tree,
+            Some(b"blob") => Kind::Blob,
+            Some(b"tag") => Kind::Tag,
+            _ => return Err(format_err!("bad loose object type: {}", hex)),
+        };
+        loose.drain(..=nul);
+        Ok(Some((kind, Rc::new(loose))))
+    }
+
+    fn read_at(&self, pack: usize, offset: u64, depth: usize) -> Result<Object, Error> {
+        if depth > MAX_DELTA_DEPTH {
+            return Err(err_msg("delta chain too long"));
+        }
+
+        if let Some(object) = self.cache.borrow().objects.get(&(pack, offset)) {
+            return Ok(object.clone());
+        }
+
+        let data = &self.packs[pack].data;
+        let (kind, size, pos) = entry_header(data, offset as usize)?;
+        let (base, pos) = match kind {
+            1..=4 => {
+                let kind = [Kind::Commit, Kind::Tree, Kind::Blob, Kind::Tag][usize::from(kind) - 1];
+                let (data, _) = inflate(data.get(pos..).ok_or_else(truncated)?, size)?;
+                return Ok((kind, Rc::new(data)));
+            }
+            6 => {
+                let (distance, pos) = ofs_delta(data, pos)?;
+                let base_offset = offset
+                    .checked_sub(distance)
+                    .ok_or_else(|| err_msg("delta base before start of pack"))?;
+                let base = self.read_at(pack, base_offset, depth + 1)?;
+                self.remember(pack, base_offset, &base);
+                (base, pos)
+            }
+            7 => {
+                let mut id = [0u8; 20];
+                id.copy_from_slice(data.get(pos..pos + 20).ok_or_else(truncated)?);
+                let base = self
+                    .read(&id, depth + 1)?
+                    .ok_or_else(|| format_err!("missing delta base: {}", digest::hex(&id)))?;
+                (base, pos + 20)
+            }
+            other => return Err(format_err!("bad pack object type: {}", other)),
+        };
+
+        let (delta, _) = inflate(data.get(pos..).ok_or_else(truncated)?, size)?;
+        Ok((base.0, Rc::new(apply_delta(&base.1, &delta)?)))
+    }
+
+    fn remember(&self, pack: usize, offset: u64, object: &Object) {
+        let mut cache = self.cache.borrow_mut();
+        if cache.bytes + object.1.len() > CACHE_BYTES {
+            cache.objects.clear();
+            cache.bytes = 0;
+        }
+        cache.bytes += object.1.len();
+        cache.objects.insert((pack, offset), object.clone());
+    }
+
+    fn read_existing(&self, id: &Id) -> Result<Object, Error> {
+        self.read(id, 0)?
+            .ok_or_else(|| format_err!("missing object: {}", digest::hex(id)))
+    }
+
+    /// Follow tags and commits to a tree, and the time it was committed, if it was.
+    fn peel(&self, id: &Id) -> Result<(Id, u64), Error> {
+        let mut id = *id;
+        for _ in 0..16 {
+            let (kind, data) = self.read_existing(&id)?;
+            let (field, committed) = match kind {
+                Kind::Tree => return Ok((id, 0)),
+                Kind::Commit => (&b"tree "[..], committed(&data)),
+                Kind::Tag => (&b"object "[..], 0),
+                Kind::Blob => return Err(err_msg("ref points at a blob")),
+            };
+
+            let line = data
+                .split(|&b| b'\n' == b)
+                .find(|line| line.starts_with(field))
+                .ok_or_else(|| format_err!("no {:?} in {:?}", field, kind))?;
+            id = parse_hex(&line[field.len()..])
+                .ok_or_else(|| format_err!("bad object id in {:?}", kind))?;
+
+            if Kind::Commit == kind {
+                return Ok((id, committed));
+            }
+        }
+
+        Err(err_msg("too many nested tags"))
+    }
+
+    fn walk_tree(
+        &self,
+        id: &Id,
+        prefix: &[u8],
+        committed: u64,
+        temps: &mut Temps,
+        entries: &mut Vec<LocalEntry>,
+        depth: usize,
+    ) -> Result<(), Error> {
+        if depth > 256 {
+            return Err(err_msg("trees nested too deeply"));
+        }
+
+        let (kind, data) = self.read_existing(id)?;
+        if Kind::Tree != kind {
+            return Err(format_err!("expected a tree, not a {:?}", kind));
+        }
+
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let space = rest.iter().position(|&b| b' ' == b);
+            let nul = rest.iter().position(|&b| 0 == b);
+            let (space, nul) = match (space, nul) {
+                (Some(space), Some(nul)) if space < nul && nul + 21 <= rest.len() => (space, nul),
+                _ => return Err(err_msg("bad tree entry")),
+            };
+
+            let mode = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+                .ok_or_else(|| err_msg("bad tree entry mode"))?;
+            let mut path = prefix.to_vec();
+            path.extend_from_slice(&rest[space + 1..nul]);
+            let mut child = [0u8; 20];
+            child.copy_from_slice(&rest[nul + 1..nul + 21]);
+            rest = &rest[nul + 21..];
+
+            match mode >> 12 {
+                0o04 => {
+                    path.push(b'/');
+                    self.walk_tree(&child, &path, committed, temps, entries, depth + 1)?;
+                }
+                // a submodule: a commit in some other repository
+                0o16 => (),
+                0o12 => {
+                    let (_, target) = self.read_existing(&child)?;
+                    entries.push(LocalEntry {
+                        temp: None,
+                        meta: meta::for_git(mode, committed, Some(&target[..])),
+                        path: path.into_boxed_slice(),
+                    });
+                }
+                _ => {
+                    let (_, content) = self.read_existing(&child)?;
+                    entries.push(LocalEntry {
+                        temp: if content.is_empty() {
+                            None
+                        } else {
+                            Some(temps.insert(&content[..])?)
+                        },
+                        meta: meta::for_git(mode, committed, None),
+                        path: path.into_boxed_slice(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn blobs(&self, temps: &mut Temps, entries: &mut Vec<LocalEntry>) -> Result<(), Error> {
+        let mut ids: Vec<Id> = self
+            .packs
+            .iter()
+            .flat_map(|pack| pack.offsets.keys().cloned())
+            .collect();
+
+        if let Some(ref objects) = self.objects {
+            for dir in fs::read_dir(objects)? {
+                let dir = dir?;
+                let prefix = dir.file_name().to_string_lossy().into_owned();
+                if 2 != prefix.len() || !dir.file_type()?.is_dir() {
+                    continue;
+                }
+                for file in fs::read_dir(dir.path())? {
+                    let name = format!("{}{}", prefix, file?.file_name().to_string_lossy());
+                    if let Some(id) = parse_hex(name.as_bytes()) {
+                        ids.push(id);
+                    }
+                }
+            }
+        }
+
+        ids.sort();
+        ids.dedup();
+
+        for id in ids {
+            let (kind, content) = self.read_existing(&id)?;
+            if Kind::Blob != kind {
+                continue;
+            }
+
+            entries.push(LocalEntry {
+                temp: if content.is_empty() {
+                    None
+                } else {
+                    Some(temps.insert(&content[..])?)
+                },
+                meta: meta::just_stream(),
+                path: digest::hex(&id).into_bytes().into_boxed_slice(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A ref (as `git rev-parse` would find it), or a full commit id.
+fn resolve(git_dir: &Path, name: &str) -> Result<Option<Id>, Error> {
+    if let Some(id) = parse_hex(name.as_bytes()) {
+        return Ok(Some(id));
+    }
+
+    for candidate in &[
+        name.to_string(),
+        format!("refs/{}", name),
+        format!("refs/tags/{}", name),
+        format!("refs/heads/{}", name),
+        format!("refs/remotes/{}", name),
+        format!("refs/remotes/{}/HEAD", name),
+    ] {
+        if let Some(id) = read_ref(git_dir, candidate, 0)? {
+            return Ok(Some(id));
+        }
+    }
+
+    Ok(None)
+}
+
+fn read_ref(git_dir: &Path, name: &str, depth: usize) -> Result<Option<Id>, Error> {
+    if depth > 8 {
+        return Err(format_err!("symbolic ref loop at {:?}", name));
+    }
+
+    let path = git_dir.join(name);
+    if path.is_file() {
+        let content = fs::read_to_string(&path)?;
+        let content = content.trim();
+        return match content.strip_prefix("ref:") {
+            Some(target) => read_ref(git_dir, target.trim(), depth + 1),
+            None => Ok(parse_hex(content.as_bytes())),
+        };
+    }
+
+    let packed = match fs::read_to_string(git_dir.join("packed-refs")) {
+        Ok(packed) => packed,
+        Err(_) => return Ok(None),
+    };
+
+    Ok(packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, ref_name)| *ref_name == name)
+        .and_then(|(id, _)| parse_hex(id.as_bytes())))
+}
+
+/// The committer's timestamp: `committer Name <email> 1700000000 +0000`.
+fn committed(commit: &[u8]) -> u64 {
+    commit
+        .split(|&b| b'\n' == b)
+        .find(|line| line.starts_with(b"committer "))
+        .and_then(|line| {
+            let line = String::from_utf8_lossy(line);
+            line.rsplit(' ')
+                .nth(1)
+                .and_then(|seconds| seconds.parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+fn id_of(kind: Kind, data: &[u8]) -> Id {
+    let name = match kind {
+        Kind::Commit => "commit",
+        Kind::Tree => "tree",
+        Kind::Blob => "blob",
+        Kind::Tag => "tag",
+    };
+    let mut sha1 = sha1::Sha1::new();
+    sha1.update(format!("{} {}\0", name, data.len()).as_bytes());
+    sha1.update(data);
+    sha1.finalize().into()
+}
+
+fn parse_hex(hex: &[u8]) -> Option<Id> {
+    if 40 != hex.len() {
+        return None;
+    }
+
+    let mut id = [0u8; 20];
+    for (byte, pair) in id.iter_mut().zip(hex.chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(id)
+}
+
+/// `PACK`, version 2 or 3, and the object count.
+fn pack_header(data: &[u8]) -> Result<usize, Error> {
+    if data.len() < 12 || !data.starts_with(b"PACK") || !(2..=3).contains(&be32(&data[4..])) {
+        return Err(err_msg("not a git pack"));
+    }
+    Ok(be32(&data[8..]) as usize)
+}
+
+/// Object ids and their offsets in the pack, from a version 1 or 2 index.
+fn parse_idx(idx: &[u8]) -> Result<HashMap<Id, u64>, Error> {
+    let v2 = idx.starts_with(b"\xfftOc");
+    if v2 && (idx.len() < 8 || 2 != be32(&idx[4..])) {
+        return Err(err_msg("unsupported index version"));
+    }
+
+    let fanout = if v2 { 8 } else { 0 };
+    let count = be32(
+        idx.get(fanout + 255 * 4..fanout + 256 * 4)
+            .ok_or_else(truncated)?,
+    ) as usize;
+    let table = fanout + 256 * 4;
+
+    // the count is only believed as far as the index has room for its ids and offsets
+    let entry_len = if v2 { 28 } else { 24 };
+    if count
+        .checked_mul(entry_len)
+        .and_then(|len| len.checked_add(table))
+        .is_none_or(|end| end > idx.len())
+    {
+        return Err(truncated());
+    }
+    let mut offsets = HashMap::with_capacity(count);
+
+    for i in 0..count {
+        let (id, offset) = if v2 {
+            let id = table + i * 20;
+            let offset = table + count * 24 + i * 4;
+            let small = be32(idx.get(offset..offset + 4).ok_or_else(truncated)?);
+            let offset = if 0 == small & 0x8000_0000 {
+                u64::from(small)
+            } else {
+                let large = table + count * 28 + (small & 0x7fff_ffff) as usize * 8;
+                let large = idx.get(large..large + 8).ok_or_else(truncated)?;
+                u64::from(be32(large)) << 32 | u64::from(be32(&large[4..]))
+            };
+            (id, offset)
+        } else {
+            let entry = table + i * 24;
+            let offset = be32(idx.get(entry..entry + 4).ok_or_else(truncated)?);
+            (entry + 4, u64::from(offset))
+        };
+
+        let mut key = [0u8; 20];
+        key.copy_from_slice(idx.get(id..id + 20).ok_or_else(truncated)?);
+        offsets.insert(key, offset);
+    }
+
+    Ok(offsets)
+}
+
+/// The type, inflated size, and where the rest starts, of the object at `offset`.
+fn entry_header(data: &[u8], offset: usize) -> Result<(u8, usize, usize), Error> {
+    let mut pos = offset;
+    let mut byte = *data.get(pos).ok_or_else(truncated)?;
+    pos += 1;
+    let kind = (byte >> 4) & 7;
+    let mut size = usize::from(byte & 0x0f);
+    let mut shift = 4;
+    while 0 != byte & 0x80 {
+        byte = *data.get(pos).ok_or_else(truncated)?;
+        pos += 1;
+        if shift > 57 {
+            return Err(err_msg("bad pack object size"));
+        }
+        size |= usize::from(byte & 0x7f) << shift;
+        shift += 7;
+    }
+    Ok((kind, size, pos))
+}
+
+/// An `OFS_DELTA`'s distance back to its base, and where the delta starts.
+fn ofs_delta(data: &[u8], mut pos: usize) -> Result<(u64, usize), Error> {
+    let mut byte = *data.get(pos).ok_or_else(truncated)?;
+    pos += 1;
+    let mut distance = u64::from(byte & 0x7f);
+    while 0 != byte & 0x80 {
+        byte = *data.get(pos).ok_or_else(truncated)?;
+        pos += 1;
+        distance = distance
+            .checked_add(1)
+            .and_then(|distance| distance.checked_mul(128))
+            .ok_or_else(|| err_msg("bad delta offset"))?
+            | u64::from(byte & 0x7f);
+    }
+    Ok((distance, pos))
+}
+
+/// Inflate a zlib stream which should produce `size` bytes, returning them and its length.
+fn inflate(input: &[u8], size: usize) -> Result<(Vec<u8>, usize), Error> {
+    let mut decoder = flate2::bufread::ZlibDecoder::new(input);
+    let mut out = Vec::with_capacity(size.min(16 * 1024 * 1024));
+    (&mut decoder).take(size as u64 + 1).read_to_end(&mut out)?;
+    if out.len() != size {
+        return Err(err_msg("pack object size mismatch"));
+    }
+    Ok((out, decoder.total_in() as usize))
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+    if delta_size(delta, &mut pos)? != base.len() {
+        return Err(err_msg("delta base size mismatch"));
+    }
+    let size = delta_size(delta, &mut pos)?;
+
+    let mut out = Vec::with_capacity(size.min(16 * 1024 * 1024));
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if 0 != op & 0x80 {
+            let mut offset = 0usize;
+            let mut len = 0usize;
+            for i in 0..7 {
+                if 0 != op & (1 << i) {
+                    let byte = usize::from(*delta.get(pos).ok_or_else(truncated)?);
+                    pos += 1;
+                    if i < 4 {
+                        offset |= byte << (8 * i);
+                    } else {
+                        len |= byte << (8 * (i - 4));
+                    }
+                }
+            }
+            if 0 == len {
+                len = 0x10000;
+            }
+            let copy = offset
+                .checked_add(len)
+                .and_then(|end| base.get(offset..end))
+                .ok_or_else(|| err_msg("delta copies from outside its base"))?;
+            out.extend_from_slice(copy);
+        } else if 0 != op {
+            let len = usize::from(op);
+            out.extend_from_slice(delta.get(pos..pos + len).ok_or_else(truncated)?);
+            pos += len;
+        } else {
+            return Err(err_msg("bad delta instruction"));
+        }
+    }
+
+    if out.len() != size {
+        return Err(err_msg("delta result size mismatch"));
+    }
+    Ok(out)
+}
+
+fn delta_size(delta: &[u8], pos: &mut usize) -> Result<usize, Error> {
+    let mut size = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *delta.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        if shift > 57 {
+            return Err(err_msg("bad delta size"));
+        }
+        size |= usize::from(byte & 0x7f) << shift;
+        shift += 7;
+        if 0 == byte & 0x80 {
+            return Ok(size);
+        }
+    }
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn truncated() -> Error {
+    err_msg("truncated git data")
+}
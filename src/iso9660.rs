@@ -0,0 +1,540 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+use failure::err_msg;
+use failure::Error;
+
+use crate::meta;
+use crate::mio::Mio;
+use crate::simple_time;
+use crate::temps::Temps;
+use crate::unpacker::LocalEntry;
+
+const SECTOR: u64 = 2048;
+/// The volume descriptors start after the 16 sector "system area"; `CD001` is one byte in.
+pub const MAGIC_OFFSET: u64 = 16 * SECTOR + 1;
+
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_ASSOCIATED: u8 = 0x04;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// Directories bigger than this are surely corrupt.
+const MAX_DIRECTORY: u32 = 16 * 1024 * 1024;
+
+/// A directory record, and anything Rock Ridge said about it.
+pub struct Record {
+    pub name: Vec<u8>,
+    pub extent: u32,
+    pub size: u32,
+    pub flags: u8,
+    /// Seconds since the epoch: Rock Ridge's modification time, or the recording date.
+    pub mtime: u64,
+    /// Rock Ridge's `PX`: mode, uid and gid.
+    pub posix: Option<(u32, u32, u32)>,
+    /// Rock Ridge's `SL`.
+    pub link: Option<Vec<u8>>,
+    /// Rock Ridge's `PN`: major and minor device numbers.
+    pub device: Option<(u32, u32)>,
+    /// Rock Ridge's `CL`: where a relocated directory, which belongs here, really is.
+    child_link: Option<u32>,
+    /// Rock Ridge's `RE`: this directory is listed elsewhere, through a `CL`.
+    relocated: bool,
+}
+
+impl Record {
+    pub fn is_dir(&self) -> bool {
+        0 != self.flags & FLAG_DIRECTORY || self.child_link.is_some()
+    }
+}
+
+/// How names are stored in the tree we're reading.
+#[derive(Clone, Copy, PartialEq)]
+enum Names {
+    /// `FOO.TXT;1`, unless Rock Ridge's `NM` says otherwise; SUSP entries start this far in.
+    Iso { susp_skip: Option<usize> },
+    /// UCS-2, big endian.
+    Joliet,
+}
+
+struct Reader<'m> {
+    from: &'m mut Mio,
+    block: u64,
+    names: Names,
+    visited: HashSet<u32>,
+}
+
+/// Rock Ridge's names are preferred, then Joliet's, then plain ISO 9660's.
+pub fn unpack(
+    mut from: Mio,
+    temps: &mut Temps,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let mut primary = None;
+    let mut joliet = None;
+
+    for index in 0..64 {
+        let descriptor = read_at(&mut from, 16 * SECTOR + index * SECTOR, SECTOR as usize)?;
+        if b"CD001" != &descriptor[1..6] {
+            return Err(err_msg("bad volume descriptor"));
+        }
+
+        match descriptor[0] {
+            1 if primary.is_none() => primary = Some(descriptor),
+            // the escape sequence of any UCS-2 level
+            2 if b"%/@" == &descriptor[88..91]
+                || b"%/C" == &descriptor[88..91]
+                || b"%/E" == &descriptor[88..91] =>
+            {
+                joliet = Some(descriptor)
+            }
+            255 => break,
+            _ => (),
+        }
+    }
+
+    let primary = primary.ok_or_else(|| err_msg("no primary volume descriptor"))?;
+    let block = u64::from(u16::from_le_bytes([primary[128], primary[129]]));
+    if !block.is_power_of_two() || block < 512 {
+        return Err(format_err!("bad logical block size: {}", block));
+    }
+
+    let mut reader = Reader {
+        from: &mut from,
+        block,
+        names: Names::Iso { susp_skip: None },
+        visited: HashSet::new(),
+    };
+
+    // the root directory's record is in the descriptor
+    let root = |descriptor: &[u8]| {
+        (
+            both_endian_32(&descriptor[158..]),
+            both_endian_32(&descriptor[166..]),
+        )
+    };
+    let (extent, size) = root(&primary);
+    let susp_skip = reader.susp_skip(extent)?;
+    let (extent, size) = match (susp_skip, joliet) {
+        (None, Some(joliet)) => {
+            reader.names = Names::Joliet;
+            root(&joliet)
+        }
+        _ => {
+            reader.names = Names::Iso { susp_skip };
+            (extent, size)
+        }
+    };
+
+    reader.walk(extent, size, b"", temps, entries, 0)
+}
+
+impl<'m> Reader<'m> {
+    /// SUSP's `SP`, at the start of the root's `.` record, says Rock Ridge is in use.
+    fn susp_skip(&mut self, root: u32) -> Result<Option<usize>, Error> {
+        let sector = read_at(self.from, u64::from(root) * self.block, 256)?;
+        let len = usize::from(sector[0]);
+        let name_len = usize::from(sector[32]);
+        let system_use = sector.get(33 + name_len + (1 - name_len % 2)..len);
+        Ok(match system_use {
+            Some(area)
+                if area.len() >= 7 && area.starts_with(b"SP") && b"\xbe\xef" == &area[4..6] =>
+            {
+                Some(usize::from(area[6]))
+            }
+            _ => None,
+        })
+    }
+
+    fn walk(
+        &mut self,
+        extent: u32,
+        size: u32,
+        prefix: &[u8],
+        temps: &mut Temps,
+        entries: &mut Vec<LocalEntry>,
+        depth: usize,
+    ) -> Result<(), Error> {
+        if depth > 255 {
+            return Err(err_msg("directories nested too deeply"));
+        }
+
+        if !self.visited.insert(extent) {
+            return Err(err_msg("directory loop"));
+        }
+
+        if size > MAX_DIRECTORY {
+            return Err(format_err!("implausible directory size: {}", size));
+        }
+
+        let start = u64::from(extent) * self.block;
+        let data = read_at(self.from, start, size as usize)?;
+        let mut extents = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let len = usize::from(data[pos]);
+            if 0 == len {
+                // records don't cross sectors, which can be bigger than blocks; the rest of
+                // this one is padding
+                let at = start + pos as u64;
+                pos = ((at / SECTOR + 1) * SECTOR - start) as usize;
+                continue;
+            }
+
+            let raw = data
+                .get(pos..pos + len)
+                .ok_or_else(|| err_msg("truncated directory record"))?;
+            pos += len;
+
+            // `.` and `..`
+            if raw.len() > 33 && 1 == raw[32] && raw[33] <= 1 {
+                continue;
+            }
+
+            let record = self.record(raw)?;
+            if record.relocated || 0 != record.flags & FLAG_ASSOCIATED {
+                continue;
+            }
+
+            let mut path = prefix.to_vec();
+            path.extend_from_slice(&record.name);
+
+            // where mkisofs relocates directories nested too deeply; we follow `CL` instead
+            let rock_ridge = match self.names {
+                Names::Iso { susp_skip } => susp_skip.is_some(),
+                Names::Joliet => false,
+            };
+            if rock_ridge && 0 == depth && record.is_dir() && b"rr_moved" == &record.name[..] {
+                continue;
+            }
+
+            if record.is_dir() {
+                let (extent, size) = match record.child_link {
+                    Some(extent) => {
+                        let dot = read_at(self.from, u64::from(extent) * self.block, 34)?;
+                        (extent, both_endian_32(&dot[10..]))
+                    }
+                    None => (record.extent, record.size),
+                };
+
+                path.push(b'/');
+                entries.push(LocalEntry {
+                    temp: None,
+                    meta: meta::for_iso(&record),
+                    path: path.clone().into_boxed_slice(),
+                });
+                self.walk(extent, size, &path, temps, entries, depth + 1)?;
+                continue;
+            }
+
+            // a big file is in several records, all but the last flagged
+            extents.push((
+                u64::from(record.extent) * self.block,
+                u64::from(record.size),
+            ));
+            if 0 != record.flags & FLAG_MULTI_EXTENT {
+                continue;
+            }
+
+            let extents = std::mem::take(&mut extents);
+            let total: u64 = extents.iter().map(|&(_, len)| len).sum();
+            let temp = if 0 == total || record.link.is_some() {
+                None
+            } else {
+                Some(temps.insert(Extents {
+                    from: &mut *self.from,
+                    extents: extents.into_iter(),
+                    left: 0,
+                })?)
+            };
+
+            entries.push(LocalEntry {
+                temp,
+                meta: meta::for_iso(&record),
+                path: path.into_boxed_slice(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, raw: &[u8]) -> Result<Record, Error> {
+        if raw.len() < 34 || raw.len() < 33 + usize::from(raw[32]) {
+            return Err(err_msg("bad directory record"));
+        }
+
+        let name_len = usize::from(raw[32]);
+        let name = &raw[33..33 + name_len];
+
+        let mut record = Record {
+            name: match self.names {
+                Names::Iso { .. } => iso_name(name),
+                Names::Joliet => joliet_name(name),
+            },
+            extent: both_endian_32(&raw[2..]),
+            size: both_endian_32(&raw[10..]),
+            flags: raw[25],
+            mtime: recording_date(&raw[18..25]),
+            posix: None,
+            link: None,
+            device: None,
+            child_link: None,
+            relocated: false,
+        };
+
+        if let Names::Iso {
+            susp_skip: Some(skip),
+        } = self.names
+        {
+            // the name is padded to an even offset
+            let start = 33 + name_len + (1 - name_len % 2) + skip;
+            if let Some(area) = raw.get(start..) {
+                let mut rock_ridge = RockRidge::default();
+                self.system_use(area, &mut record, &mut rock_ridge, 0)?;
+                if let Some(name) = rock_ridge.name {
+                    record.name = name;
+                }
+            }
+        }
+
+        if record.name.is_empty() || record.name.contains(&b'/') {
+            return Err(format_err!(
+                "bad name: {:?}",
+                String::from_utf8_lossy(&record.name)
+            ));
+        }
+
+        Ok(record)
+    }
+
+    fn system_use(
+        &mut self,
+        area: &[u8],
+        record: &mut Record,
+        rock_ridge: &mut RockRidge,
+        depth: usize,
+    ) -> Result<(), Error> {
+        let mut pos = 0;
+        while pos + 4 <= area.len() {
+            let len = usize::from(area[pos + 2]);
+            if len < 4 || pos + len > area.len() {
+                break;
+            }
+            let signature = &area[pos..pos + 2];
+            let data = &area[pos + 4..pos + len];
+            pos += len;
+
+            match signature {
+                // `.` and `..` flags: not names we use
+                b"NM" if !data.is_empty() && 0 == data[0] & 0x06 => {
+                    rock_ridge
+                        .name
+                        .get_or_insert_with(Vec::new)
+                        .extend_from_slice(&data[1..]);
+                }
+                b"PX" if data.len() >= 32 => {
+                    record.posix = Some((
+                        both_endian_32(&data[0..]),
+                        both_endian_32(&data[16..]),
+                        both_endian_32(&data[24..]),
+                    ));
+                }
+                b"PN" if data.len() >= 16 => {
+                    let (high, low) = (both_endian_32(data), both_endian_32(&data[8..]));
+                    // some writers leave `high` empty, and put all of a 16-bit `dev_t` in `low`
+                    record.device = Some(if 0 == high && 0 != low & !0xff {
+                        (low >> 8, low & 0xff)
+                    } else {
+                        (high, low)
+                    });
+                }
+                b"SL" if !data.is_empty() => {
+                    let link = record.link.get_or_insert_with(Vec::new);
+                    rock_ridge.link_components(link, &data[1..]);
+                }
+                b"TF" if !data.is_empty() => {
+                    let flags = data[0];
+                    let width = if 0 != flags & 0x80 { 17 } else { 7 };
+                    let mut at = 1;
+                    // creation, modification, access, ...: only those present are stored
+                    for bit in 0..7 {
+                        if 0 == flags & (1 << bit) {
+                            continue;
+                        }
+                        if 1 == bit {
+                            if let Some(stamp) = data.get(at..at + width) {
+                                record.mtime = if 17 == width {
+                                    volume_date(stamp)
+                                } else {
+                                    recording_date(stamp)
+                                };
+                            }
+                        }
+                        at += width;
+                    }
+                }
+                b"CL" if data.len() >= 8 => record.child_link = Some(both_endian_32(data)),
+                b"RE" => record.relocated = true,
+                b"CE" if data.len() >= 24 && depth < 16 => {
+                    let offset = u64::from(both_endian_32(data)) * self.block
+                        + u64::from(both_endian_32(&data[8..]));
+                    let len = both_endian_32(&data[16..]);
+                    if u64::from(len) > self.block {
+                        return Err(err_msg("bad continuation area"));
+                    }
+                    let continued = read_at(self.from, offset, len as usize)?;
+                    self.system_use(&continued, record, rock_ridge, depth + 1)?;
+                }
+                b"ST" => break,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct RockRidge {
+    name: Option<Vec<u8>>,
+    /// The last symlink component continues in the next.
+    link_continues: bool,
+}
+
+impl RockRidge {
+    fn link_components(&mut self, link: &mut Vec<u8>, mut components: &[u8]) {
+        while components.len() >= 2 {
+            let flags = components[0];
+            let len = usize::from(components[1]);
+            let content = match components.get(2..2 + len) {
+                Some(content) => content,
+                None => return,
+            };
+            components = &components[2 + len..];
+
+            let part: &[u8] = if 0 != flags & 0x02 {
+                b"."
+            } else if 0 != flags & 0x04 {
+                b".."
+            } else if 0 != flags & 0x08 {
+                b"/"
+            } else {
+                content
+            };
+
+            if !link.is_empty() && !self.link_continues && Some(&b'/') != link.last() {
+                link.push(b'/');
+            }
+            link.extend_from_slice(part);
+            self.link_continues = 0 != flags & 0x01;
+        }
+    }
+}
+
+/// Reads a file's extents in turn.
+struct Extents<'m> {
+    from: &'m mut Mio,
+    extents: std::vec::IntoIter<(u64, u64)>,
+    left: u64,
+}
+
+impl<'m> Read for Extents<'m> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while 0 == self.left {
+            match self.extents.next() {
+                Some((offset, len)) => {
+                    self.from.seek(SeekFrom::Start(offset))?;
+                    self.left = len;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        let found = self.from.read(&mut buf[..len])?;
+        if 0 == found {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file extends beyond the image",
+            ));
+        }
+        self.left -= found as u64;
+        Ok(found)
+    }
+}
+
+fn read_at(from: &mut Mio, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    from.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; len];
+    from.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Both-endian fields store the little-endian value first.
+fn both_endian_32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// `FOO.TXT;1` is `FOO.TXT`, and `FOO.;1` is `FOO`.
+fn iso_name(name: &[u8]) -> Vec<u8> {
+    let name = match name.iter().rposition(|&b| b';' == b) {
+        Some(semicolon) => &name[..semicolon],
+        None => name,
+    };
+    name.strip_suffix(b".").unwrap_or(name).to_vec()
+}
+
+fn joliet_name(name: &[u8]) -> Vec<u8> {
+    let units: Vec<u16> = name
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let name = String::from_utf16_lossy(&units);
+    let name = match name.rfind(';') {
+        Some(semicolon) => &name[..semicolon],
+        None => &name[..],
+    };
+    name.as_bytes().to_vec()
+}
+
+/// Years since 1900, month, day, hour, minute, second, and the offset from GMT in 15 minutes.
+fn recording_date(stamp: &[u8]) -> u64 {
+    if stamp.iter().all(|&b| 0 == b) {
+        return 0;
+    }
+
+    let at = |i: usize| i64::from(stamp[i]);
+    let seconds = simple_time::utc_epoch_seconds(1900 + at(0), at(1), at(2), at(3), at(4), at(5));
+    u64::try_from(seconds - i64::from(stamp[6] as i8) * 15 * 60).unwrap_or(0)
+}
+
+/// `YYYYMMDDHHMMSScc` in digits, then the offset from GMT in 15 minutes.
+fn volume_date(stamp: &[u8]) -> u64 {
+    let digits = |from: usize, to: usize| -> Option<i64> {
+        std::str::from_utf8(&stamp[from..to]).ok()?.parse().ok()
+    };
+
+    let seconds = match (
+        digits(0, 4),
+        digits(4, 6),
+        digits(6, 8),
+        digits(8, 10),
+        digits(10, 12),
+        digits(12, 14),
+    ) {
+        (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second))
+            if year > 0 =>
+        {
+            simple_time::utc_epoch_seconds(year, month, day, hour, minute, second)
+        }
+        _ => return 0,
+    };
+
+    u64::try_from(seconds - i64::from(stamp[16] as i8) * 15 * 60).unwrap_or(0)
+}
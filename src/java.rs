@@ -0,0 +1,138 @@
+use std::io::Read;
+
+use crate::deb;
+use crate::package;
+use crate::unpacker::Entry;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JavaKind {
+    Jar,
+    /// Web application: `WEB-INF/web.xml`, or a manifest and `WEB-INF/{classes,lib}/`.
+    War,
+    /// Enterprise application: `META-INF/application.xml`.
+    Ear,
+    /// Android library: `AndroidManifest.xml` and `classes.jar`.
+    Aar,
+}
+
+/// What a Java archive says about itself.
+#[derive(Clone, Debug)]
+pub struct JavaInfo {
+    pub kind: JavaKind,
+    /// The main section of `META-INF/MANIFEST.MF`, in file order.
+    pub manifest: Vec<(String, String)>,
+    /// From each `META-INF/maven/**/pom.properties`; fat jars may have many.
+    pub maven: Vec<MavenCoordinates>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MavenCoordinates {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+}
+
+impl JavaInfo {
+    /// A manifest attribute, by case-insensitive name.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.manifest
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn identify(children: &[Entry]) -> Option<JavaInfo> {
+    let has = |path: &[u8]| package::child(children, path).is_some();
+    let has_prefix = |prefix: &[u8]| {
+        children
+            .iter()
+            .any(|entry| entry.local.path.starts_with(prefix))
+    };
+
+    let kind = if has(b"WEB-INF/web.xml")
+        || has(b"META-INF/MANIFEST.MF")
+            && (has_prefix(b"WEB-INF/classes/") || has_prefix(b"WEB-INF/lib/"))
+    {
+        JavaKind::War
+    } else if has(b"META-INF/application.xml") {
+        JavaKind::Ear
+    } else if has(b"AndroidManifest.xml") && has(b"classes.jar") {
+        JavaKind::Aar
+    } else if has(b"META-INF/MANIFEST.MF") {
+        JavaKind::Jar
+    } else {
+        return None;
+    };
+
+    let manifest = package::read_child(children, b"META-INF/MANIFEST.MF")
+        .map(parse_manifest)
+        .unwrap_or_default();
+
+    let maven = children
+        .iter()
+        .filter(|entry| {
+            entry.local.path.starts_with(b"META-INF/maven/")
+                && entry.local.path.ends_with(b"/pom.properties")
+        })
+        .filter_map(|entry| std::fs::File::open(entry.local.temp.as_ref()?).ok())
+        .map(parse_pom_properties)
+        .collect();
+
+    Some(JavaInfo {
+        kind,
+        manifest,
+        maven,
+    })
+}
+
+/// The main section's attributes. Lines are wrapped at 72 bytes, with continuations
+/// starting with a single space, which is removed before joining.
+pub fn parse_manifest<R: Read>(from: R) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = Vec::new();
+
+    for line in deb::lines(from) {
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, value)) = attributes.last_mut() {
+                value.push_str(continuation);
+            }
+            continue;
+        }
+
+        if let Some(colon) = line.find(": ") {
+            attributes.push((line[..colon].to_string(), line[colon + 2..].to_string()));
+        }
+    }
+
+    attributes
+}
+
+pub fn parse_pom_properties<R: Read>(from: R) -> MavenCoordinates {
+    let mut coordinates = MavenCoordinates::default();
+
+    for line in deb::lines(from) {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+
+        let split = match line.find(['=', ':']) {
+            Some(split) => split,
+            None => continue,
+        };
+
+        let value = line[split + 1..].trim().to_string();
+        match line[..split].trim() {
+            "groupId" => coordinates.group_id = value,
+            "artifactId" => coordinates.artifact_id = value,
+            "version" => coordinates.version = value,
+            _ => (),
+        }
+    }
+
+    coordinates
+}
@@ -0,0 +1,303 @@
+use crate::file_type::FileType;
+
+/// What we think a file is, as a human would describe it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Classification {
+    /// For text files we recognise.
+    pub language: Option<Language>,
+    pub verdict: Verdict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    /// Probably written, and read, by people.
+    Text,
+    /// Text, but the output of a tool: minified, a lock file, a `configure` script, ...
+    Generated,
+    Binary,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Language {
+    Assembly,
+    Awk,
+    C,
+    CMake,
+    Cpp,
+    CSharp,
+    Css,
+    Dockerfile,
+    Go,
+    Groovy,
+    Haskell,
+    Html,
+    Java,
+    JavaScript,
+    Json,
+    Kotlin,
+    Lua,
+    Makefile,
+    Markdown,
+    ObjectiveC,
+    Perl,
+    Php,
+    Python,
+    Ruby,
+    Rust,
+    Scala,
+    Shell,
+    Sql,
+    Swift,
+    Tcl,
+    Toml,
+    TypeScript,
+    Xml,
+    Yaml,
+}
+
+/// Exact file names, which beat any extension.
+const NAMES: &[(&str, Language)] = &[
+    ("Makefile", Language::Makefile),
+    ("makefile", Language::Makefile),
+    ("GNUmakefile", Language::Makefile),
+    ("CMakeLists.txt", Language::CMake),
+    ("Dockerfile", Language::Dockerfile),
+    ("Containerfile", Language::Dockerfile),
+    ("Rakefile", Language::Ruby),
+    ("Gemfile", Language::Ruby),
+    ("Cargo.lock", Language::Toml),
+    ("configure", Language::Shell),
+];
+
+const EXTENSIONS: &[(&str, Language)] = &[
+    ("s", Language::Assembly),
+    ("S", Language::Assembly),
+    ("asm", Language::Assembly),
+    ("awk", Language::Awk),
+    ("c", Language::C),
+    ("h", Language::C),
+    ("cmake", Language::CMake),
+    ("cc", Language::Cpp),
+    ("cpp", Language::Cpp),
+    ("cxx", Language::Cpp),
+    ("hh", Language::Cpp),
+    ("hpp", Language::Cpp),
+    ("hxx", Language::Cpp),
+    ("cs", Language::CSharp),
+    ("css", Language::Css),
+    ("go", Language::Go),
+    ("groovy", Language::Groovy),
+    ("gradle", Language::Groovy),
+    ("hs", Language::Haskell),
+    ("htm", Language::Html),
+    ("html", Language::Html),
+    ("java", Language::Java),
+    ("js", Language::JavaScript),
+    ("mjs", Language::JavaScript),
+    ("cjs", Language::JavaScript),
+    ("jsx", Language::JavaScript),
+    ("json", Language::Json),
+    ("kt", Language::Kotlin),
+    ("kts", Language::Kotlin),
+    ("lua", Language::Lua),
+    ("mk", Language::Makefile),
+    ("md", Language::Markdown),
+    ("markdown", Language::Markdown),
+    ("m", Language::ObjectiveC),
+    ("mm", Language::ObjectiveC),
+    ("pl", Language::Perl),
+    ("pm", Language::Perl),
+    ("php", Language::Php),
+    ("py", Language::Python),
+    ("pyi", Language::Python),
+    ("rb", Language::Ruby),
+    ("gemspec", Language::Ruby),
+    ("rs", Language::Rust),
+    ("scala", Language::Scala),
+    ("sh", Language::Shell),
+    ("bash", Language::Shell),
+    ("zsh", Language::Shell),
+    ("sql", Language::Sql),
+    ("swift", Language::Swift),
+    ("tcl", Language::Tcl),
+    ("toml", Language::Toml),
+    ("ts", Language::TypeScript),
+    ("tsx", Language::TypeScript),
+    ("xml", Language::Xml),
+    ("xsd", Language::Xml),
+    ("xsl", Language::Xml),
+    ("pom", Language::Xml),
+    ("svg", Language::Xml),
+    ("yaml", Language::Yaml),
+    ("yml", Language::Yaml),
+];
+
+/// Shebang interpreters, with any version suffix removed.
+const INTERPRETERS: &[(&str, Language)] = &[
+    ("sh", Language::Shell),
+    ("bash", Language::Shell),
+    ("dash", Language::Shell),
+    ("ksh", Language::Shell),
+    ("zsh", Language::Shell),
+    ("python", Language::Python),
+    ("perl", Language::Perl),
+    ("ruby", Language::Ruby),
+    ("node", Language::JavaScript),
+    ("nodejs", Language::JavaScript),
+    ("php", Language::Php),
+    ("lua", Language::Lua),
+    ("tclsh", Language::Tcl),
+    ("wish", Language::Tcl),
+    ("awk", Language::Awk),
+    ("gawk", Language::Awk),
+];
+
+/// Markers tools leave in what they write.
+const GENERATED: &[&str] = &[
+    "@generated",
+    "DO NOT EDIT",
+    "Code generated by",
+    "automatically generated",
+    "Automatically generated",
+    "autogenerated",
+    "Generated by GNU Autoconf",
+    "generated by automake",
+];
+
+const LOCK_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Gemfile.lock",
+    "poetry.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+/// Classify a file from the name it was stored under, its type, and the start of its content.
+pub fn classify(name: &[u8], file_type: FileType, header: &[u8]) -> Classification {
+    match file_type {
+        FileType::Source | FileType::Other | FileType::Empty => (),
+        _ => {
+            return Classification {
+                language: None,
+                verdict: Verdict::Binary,
+            }
+        }
+    }
+
+    let name = String::from_utf8_lossy(name);
+    let name = name.rsplit('/').next().unwrap_or(&name);
+    let text = String::from_utf8_lossy(header);
+
+    let language = by_name(name)
+        .or_else(|| shebang(&text))
+        .or_else(|| by_content(&text))
+        .map(|language| match language {
+            Language::C if looks_like_cpp(&text) => Language::Cpp,
+            other => other,
+        });
+
+    Classification {
+        language,
+        verdict: if is_generated(name, &text) {
+            Verdict::Generated
+        } else {
+            Verdict::Text
+        },
+    }
+}
+
+fn by_name(name: &str) -> Option<Language> {
+    if let Some(&(_, language)) = NAMES.iter().find(|(known, _)| name == *known) {
+        return Some(language);
+    }
+
+    let (_, extension) = name.rsplit_once('.')?;
+    EXTENSIONS
+        .iter()
+        .find(|(known, _)| extension == *known)
+        .or_else(|| {
+            EXTENSIONS
+                .iter()
+                .find(|(known, _)| extension.eq_ignore_ascii_case(known))
+        })
+        .map(|&(_, language)| language)
+}
+
+/// `#!/bin/sh`, `#!/usr/bin/env python3 -u`, `#! /usr/bin/perl -w`, ...
+fn shebang(text: &str) -> Option<Language> {
+    let line = text.strip_prefix("#!")?.lines().next()?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if "env" == interpreter {
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || '.' == c);
+
+    INTERPRETERS
+        .iter()
+        .find(|(known, _)| interpreter == *known)
+        .map(|&(_, language)| language)
+}
+
+fn by_content(text: &str) -> Option<Language> {
+    let trimmed = text.trim_start();
+    let has = |needle: &str| text.contains(needle);
+    let starts = |prefix: &str| trimmed.starts_with(prefix);
+    let any_line = |matches: fn(&str) -> bool| text.lines().map(str::trim).any(matches);
+
+    Some(if starts("<?php") {
+        Language::Php
+    } else if starts("<?xml") {
+        Language::Xml
+    } else if starts("<!DOCTYPE html") || starts("<!doctype html") || starts("<html") {
+        Language::Html
+    } else if has("\npackage ") && has("\nfunc ") || starts("package main") {
+        Language::Go
+    } else if has("fn main(")
+        || has("\nuse std::")
+        || has("\npub fn ")
+        || any_line(|line| {
+            (line.starts_with("impl ") || line.starts_with("impl<")) && line.ends_with('{')
+        })
+    {
+        Language::Rust
+    } else if starts("package ") && has(";") || has("import java.") || has("public class ") {
+        Language::Java
+    } else if has("#include <") || has("#include \"") {
+        Language::C
+    } else if any_line(|line| line.starts_with("def ") && line.ends_with(':'))
+        || starts("from ") && has(" import ")
+    {
+        Language::Python
+    } else {
+        return None;
+    })
+}
+
+fn looks_like_cpp(text: &str) -> bool {
+    [
+        "namespace ",
+        "std::",
+        "template <",
+        "template<",
+        "#include <iostream>",
+    ]
+    .iter()
+    .any(|marker| text.contains(marker))
+}
+
+fn is_generated(name: &str, text: &str) -> bool {
+    if LOCK_FILES.contains(&name) || name.contains(".min.") {
+        return true;
+    }
+
+    if GENERATED.iter().any(|marker| text.contains(marker)) {
+        return true;
+    }
+
+    // minified: a whole header without a line break
+    text.len() >= 1024 && !text.contains('\n')
+}
@@ -1,8 +1,6 @@
 #[macro_use]
 extern crate failure;
 
-extern crate time as crates_time;
-
 #[macro_use]
 extern crate more_asserts;
 
@@ -11,39 +9,158 @@ use std::path::PathBuf;
 
 use failure::Error;
 
+mod apk;
+mod deb;
+mod details;
+mod digest;
+mod dsc;
+mod executable;
 mod file_type;
-mod fill_read;
+mod gem;
+mod git;
+mod iso9660;
+mod java;
+mod language;
+mod license;
+mod lzip;
+mod lzop;
+mod lzw;
 mod meta;
 mod mio;
+mod npm;
+mod options;
+mod package;
+mod python;
+mod sbom;
 mod simple_time;
 mod temps;
 mod unpacker;
 
+pub use crate::apk::ApkInfo;
+pub use crate::deb::verify_md5sums;
+pub use crate::deb::DebControl;
+pub use crate::deb::DebInfo;
+pub use crate::deb::Md5Sum;
+pub use crate::details::ArFlavour;
+pub use crate::details::ArIndex;
+pub use crate::details::ClassVersion;
+pub use crate::details::Details;
+pub use crate::digest::Digests;
+pub use crate::dsc::Dsc;
+pub use crate::dsc::DscFile;
+pub use crate::executable::required_libraries;
+pub use crate::executable::ElfDynamic;
+pub use crate::executable::Endianness;
+pub use crate::executable::Executable;
+pub use crate::executable::ExecutableKind;
+pub use crate::executable::Hardening;
+pub use crate::executable::Relro;
+pub use crate::file_type::identify;
+pub use crate::file_type::is_probably_tar;
+pub use crate::file_type::Confidence;
+pub use crate::file_type::Evidence;
+pub use crate::file_type::FileType;
+pub use crate::file_type::Identification;
+pub use crate::file_type::TypeHint;
+pub use crate::file_type::ZipKind;
+pub use crate::file_type::ZIP_TRAILER_CAP;
+pub use crate::gem::GemDependency;
+pub use crate::gem::GemInfo;
+pub use crate::git::GitView;
+pub use crate::java::JavaInfo;
+pub use crate::java::JavaKind;
+pub use crate::java::MavenCoordinates;
+pub use crate::language::Classification;
+pub use crate::language::Language;
+pub use crate::language::Verdict;
+pub use crate::license::license_summary;
+pub use crate::license::License;
+pub use crate::license::LicenseSource;
+pub use crate::meta::Encryption;
 pub use crate::meta::ItemType;
+pub use crate::npm::NpmInfo;
+pub use crate::options::Options;
+pub use crate::package::Package;
+pub use crate::package::Verification;
+pub use crate::python::verify_record;
+pub use crate::python::PythonInfo;
+pub use crate::python::PythonKind;
 pub use crate::unpacker::Entry;
 pub use crate::unpacker::Status;
 
 pub struct Unpack {
-    status: Status,
+    unpacked: Unpacked,
     dir: tempfile::TempDir,
 }
 
+enum Unpacked {
+    Directory(Status),
+    File(Box<Entry>),
+}
+
 impl Unpack {
     pub fn unpack_into<P: AsRef<Path>, F: AsRef<Path>>(what: F, root: P) -> Result<Unpack, Error> {
+        Unpack::unpack_into_with(what, root, &Options::default())
+    }
+
+    pub fn unpack_into_with<P: AsRef<Path>, F: AsRef<Path>>(
+        what: F,
+        root: P,
+        options: &Options,
+    ) -> Result<Unpack, Error> {
         let mut temps = temps::Temps::new_in(root)?;
+        let unpacked = if what.as_ref().is_dir() {
+            Unpacked::Directory(unpacker::unpack_root(what, &mut temps, options)?)
+        } else {
+            Unpacked::File(Box::new(unpacker::unpack_file(what, &mut temps, options)?))
+        };
+
         Ok(Unpack {
-            status: unpacker::unpack_root(what, &mut temps)?,
+            unpacked,
             dir: temps.into_dir(),
         })
     }
 
+    /// What was found: a directory's files, or what a single file unpacked to.
     pub fn status(&self) -> &Status {
-        &self.status
+        match self.unpacked {
+            Unpacked::Directory(ref status) => status,
+            Unpacked::File(ref entry) => &entry.children,
+        }
+    }
+
+    /// The entry for the input itself, if it was a single file, e.g. with a `.deb`'s package,
+    /// for `verify_md5sums`. Its children are `status()`.
+    pub fn root(&self) -> Option<&Entry> {
+        match self.unpacked {
+            Unpacked::Directory(_) => None,
+            Unpacked::File(ref entry) => Some(entry),
+        }
+    }
+
+    /// A single file is included in its own right, with any package it is.
+    fn top(&self) -> &[Entry] {
+        match self.unpacked {
+            Unpacked::Directory(ref status) => status.entries(),
+            Unpacked::File(ref entry) => std::slice::from_ref(entry),
+        }
+    }
+
+    /// An SPDX 2.3 JSON document listing every package and file found, called `name`.
+    /// Files only have checksums if `Options::digests` was set.
+    pub fn spdx_json(&self, name: &str) -> String {
+        sbom::spdx(self.top(), name)
+    }
+
+    /// A CycloneDX 1.5 JSON BOM listing every package and file found, called `name`.
+    /// Files only have hashes if `Options::digests` was set.
+    pub fn cyclonedx_json(&self, name: &str) -> String {
+        sbom::cyclonedx(self.top(), name)
     }
 
     /// causes the temporary files to not be deleted
     pub fn into_path(self) -> PathBuf {
-        self.dir.into_path()
+        self.dir.keep()
     }
 }
 
@@ -56,11 +173,16 @@ pub fn print(entries: &[Entry], depth: usize) {
             entry.local.temp
         );
 
-        if let Status::Success(ref children) = entry.children {
-            println!();
-            print(children, depth + 2);
-        } else {
-            println!(" {:?}", entry.children);
+        match entry.children {
+            Status::Success(ref children) => {
+                println!();
+                print(children, depth + 2);
+            }
+            Status::Partial(ref children, ref error) => {
+                println!(" partial: {}", error);
+                print(children, depth + 2);
+            }
+            ref other => println!(" {:?}", other),
         }
     }
 }
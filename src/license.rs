@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::unpacker::Entry;
+
+/// A licence found in a file's content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct License {
+    /// An SPDX licence expression, e.g. `MIT`, `GPL-2.0-or-later`, `Apache-2.0 OR MIT`.
+    pub expression: String,
+    pub source: LicenseSource,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LicenseSource {
+    /// An `SPDX-License-Identifier:` tag, taken verbatim if it only has expression characters.
+    Tag,
+    /// A recognised notice, typically in a header comment.
+    Header,
+    /// The full text, in a file named like `LICENSE` or `COPYING`.
+    LicenseFile,
+}
+
+/// Licences are generally stated near the top; even the GPL's full text fits.
+const SCAN_LIMIT: u64 = 64 * 1024;
+
+/// Phrases, all of which must appear (as `normalise` leaves them), and the licence they
+/// identify. Among rules sharing a first phrase, the more specific comes first.
+#[rustfmt::skip]
+const RULES: &[(&[&str], &str)] = &[
+    (&["gnu affero general public license", "version 3"], "AGPL-3.0"),
+    (&["gnu lesser general public license", "version 3"], "LGPL-3.0"),
+    (&["gnu lesser general public license", "version 2 1"], "LGPL-2.1"),
+    (&["gnu library general public license", "version 2"], "LGPL-2.0"),
+    (&["gnu general public license", "version 3"], "GPL-3.0"),
+    (&["gnu general public license", "version 2"], "GPL-2.0"),
+    (&["apache license", "version 2 0"], "Apache-2.0"),
+    (&["mozilla public license", "2 0"], "MPL-2.0"),
+    (&["eclipse public license", "v 2 0"], "EPL-2.0"),
+    (&["eclipse public license", "version 2 0"], "EPL-2.0"),
+    (&["eclipse public license", "v 1 0"], "EPL-1.0"),
+    (&["eclipse public license", "version 1 0"], "EPL-1.0"),
+    (&["boost software license", "version 1 0"], "BSL-1.0"),
+    (&["cc0 1 0 universal"], "CC0-1.0"),
+    (&["this is free and unencumbered software released into the public domain"], "Unlicense"),
+    (&["redistribution and use in source and binary forms", "all advertising materials"], "BSD-4-Clause"),
+    (&["redistribution and use in source and binary forms", "neither the name"], "BSD-3-Clause"),
+    (&["redistribution and use in source and binary forms", "the names of its contributors may not"], "BSD-3-Clause"),
+    (&["redistribution and use in source and binary forms"], "BSD-2-Clause"),
+    (&["permission to use copy modify and or distribute this software for any purpose"], "ISC"),
+    (&["permission to use copy modify and distribute this software for any purpose"], "ISC"),
+    (&["permission is hereby granted free of charge to any person obtaining a copy"], "MIT"),
+    (&["altered source versions must be plainly marked", "provided as is"], "Zlib"),
+    (&["licensed under the mit license"], "MIT"),
+];
+
+/// These have `-only` and `-or-later` forms in SPDX.
+const GNU: &[&str] = &[
+    "AGPL-3.0", "LGPL-3.0", "LGPL-2.1", "LGPL-2.0", "GPL-3.0", "GPL-2.0",
+];
+
+/// Scan a file, given the name it was stored under.
+pub fn of<P: AsRef<Path>>(name: &[u8], path: P) -> Option<License> {
+    let mut text = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(SCAN_LIMIT)
+        .read_to_end(&mut text)
+        .ok()?;
+    scan(name, &String::from_utf8_lossy(&text))
+}
+
+pub fn scan(name: &[u8], text: &str) -> Option<License> {
+    if let Some(expression) = tag(text) {
+        return Some(License {
+            expression,
+            source: LicenseSource::Tag,
+        });
+    }
+
+    // padded, so phrases only match whole words
+    let text = format!(" {} ", normalise(text));
+    let find = |phrase: &str| text.find(&format!(" {} ", phrase));
+
+    // Full texts mention their relatives, e.g. the GPL suggests the LGPL, so the rule
+    // whose title comes first wins; then the earlier rule, for shared titles.
+    let id = RULES
+        .iter()
+        .filter(|(phrases, _)| phrases.iter().all(|phrase| find(phrase).is_some()))
+        .min_by_key(|(phrases, _)| find(phrases[0]))?
+        .1;
+
+    let source = if is_license_file(name) {
+        LicenseSource::LicenseFile
+    } else {
+        LicenseSource::Header
+    };
+
+    // The full texts don't say which the project intended; only a notice does.
+    let expression = if !GNU.contains(&id) {
+        id.to_string()
+    } else if LicenseSource::Header == source && find("any later version").is_some() {
+        format!("{}-or-later", id)
+    } else {
+        format!("{}-only", id)
+    };
+
+    Some(License { expression, source })
+}
+
+/// How many entries, at any depth, have each licence expression.
+pub fn license_summary(entries: &[Entry]) -> BTreeMap<String, usize> {
+    let mut summary = BTreeMap::new();
+    summarise(entries, &mut summary);
+    summary
+}
+
+fn summarise(entries: &[Entry], summary: &mut BTreeMap<String, usize>) {
+    for entry in entries {
+        if let Some(ref license) = entry.license {
+            *summary.entry(license.expression.clone()).or_insert(0) += 1;
+        }
+        summarise(entry.children.entries(), summary);
+    }
+}
+
+/// e.g. `LICENSE`, `COPYING.LIB`, `licence.md`, `LICENSE-MIT`, at any depth.
+pub fn is_license_file(name: &[u8]) -> bool {
+    let name = name.rsplit(|&b| b'/' == b).next().unwrap_or(name);
+    let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+    ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// The first tag followed by something which could be an expression, e.g. not one in a
+/// string literal in a tool which looks for them.
+fn tag(text: &str) -> Option<String> {
+    const TAG: &str = "SPDX-License-Identifier:";
+    text.match_indices(TAG).find_map(|(start, _)| {
+        let line = text[start + TAG.len()..].lines().next()?;
+        let expression = line
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim_end_matches("*)")
+            .trim();
+
+        let plausible = expression
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " .-+:()".contains(c));
+
+        if expression.is_empty() || !plausible {
+            None
+        } else {
+            Some(expression.to_string())
+        }
+    })
+}
+
+/// Lower-case words separated by single spaces, so comment markers, punctuation and
+/// line wrapping don't matter.
+fn normalise(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,115 @@
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+
+use xz2::stream::Action;
+use xz2::stream::Status;
+use xz2::stream::Stream;
+
+use crate::mio;
+
+/// `LZIP`, version, coded dictionary size.
+const HEADER_LEN: usize = 6;
+/// CRC32, data size, member size.
+const TRAILER_LEN: usize = 20;
+
+/// Decodes each member of an lzip file in turn. A member is a plain LZMA stream with fixed
+/// properties and an end marker, so liblzma's LZMA-alone decoder handles it, given a
+/// header. Trailers aren't checked, and anything after the last member is ignored.
+pub struct LzipDecoder<R> {
+    inner: R,
+    stream: Option<Stream>,
+    /// The made-up LZMA-alone header, still to be fed to the stream.
+    header: Vec<u8>,
+    first: bool,
+}
+
+impl<R: BufRead> LzipDecoder<R> {
+    pub fn new(inner: R) -> LzipDecoder<R> {
+        LzipDecoder {
+            inner,
+            stream: None,
+            header: Vec::new(),
+            first: true,
+        }
+    }
+
+    /// `false` if there are no more members.
+    fn start_member(&mut self) -> io::Result<bool> {
+        let header = mio::fill_buf(&mut self.inner)?;
+        if header.len() < HEADER_LEN || !header.starts_with(b"LZIP") {
+            return if self.first {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "not lzip"))
+            } else {
+                Ok(false)
+            };
+        }
+
+        if 1 != header[4] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported lzip version: {}", header[4]),
+            ));
+        }
+
+        let base = 1u32 << (header[5] & 0x1f);
+        let dictionary = base - (base / 16) * u32::from(header[5] >> 5);
+        self.inner.consume(HEADER_LEN);
+
+        // lc=3, lp=0, pb=2; then the dictionary size, and an unknown length
+        let mut alone = vec![0x5d];
+        alone.extend_from_slice(&dictionary.to_le_bytes());
+        alone.extend_from_slice(&[0xff; 8]);
+
+        self.stream = Some(Stream::new_lzma_decoder(u64::MAX)?);
+        self.header = alone;
+        self.first = false;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for LzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.stream.is_none() && !self.start_member()? {
+                return Ok(0);
+            }
+
+            let stream = self.stream.as_mut().expect("just started");
+            let from_header = !self.header.is_empty();
+            let input = if from_header {
+                &self.header[..]
+            } else {
+                self.inner.fill_buf()?
+            };
+            let eof = input.is_empty();
+            let before_in = stream.total_in();
+            let before_out = stream.total_out();
+            let status = stream.process(input, buf, Action::Run)?;
+            let consumed = (stream.total_in() - before_in) as usize;
+            let read = (stream.total_out() - before_out) as usize;
+            if from_header {
+                self.header.drain(..consumed);
+            } else {
+                self.inner.consume(consumed);
+            }
+
+            if Status::StreamEnd == status {
+                self.stream = None;
+                let mut trailer = [0u8; TRAILER_LEN];
+                self.inner.read_exact(&mut trailer)?;
+            }
+
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            if self.stream.is_some() && (eof || 0 == consumed) {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated lzip member",
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::io;
+use std::io::Read;
+
+const MAGIC: [u8; 9] = [0x89, b'L', b'Z', b'O', 0x00, 0x0d, 0x0a, 0x1a, 0x0a];
+
+const F_ADLER32_D: u32 = 0x0000_0001;
+const F_ADLER32_C: u32 = 0x0000_0002;
+const F_H_EXTRA_FIELD: u32 = 0x0000_0040;
+const F_CRC32_D: u32 = 0x0000_0100;
+const F_CRC32_C: u32 = 0x0000_0200;
+const F_H_FILTER: u32 = 0x0000_0800;
+
+/// lzop refuses to write larger blocks, so this bounds what we'll allocate.
+const MAX_BLOCK: usize = 64 * 1024 * 1024;
+
+/// Decodes the first file in an lzop archive. Checksums aren't verified.
+pub struct LzopDecoder<R> {
+    inner: R,
+    flags: Option<u32>,
+    block: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> LzopDecoder<R> {
+    pub fn new(inner: R) -> LzopDecoder<R> {
+        LzopDecoder {
+            inner,
+            flags: None,
+            block: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<u32> {
+        let mut magic = [0u8; 9];
+        self.inner.read_exact(&mut magic)?;
+        if MAGIC != magic {
+            return Err(invalid("not lzop"));
+        }
+
+        let version = self.u16()?;
+        let _lib_version = self.u16()?;
+        if version >= 0x0940 {
+            let _version_needed = self.u16()?;
+        }
+
+        let method = self.u8()?;
+        if !(1..=3).contains(&method) {
+            return Err(invalid(format!("unsupported lzop method: {}", method)));
+        }
+
+        if version >= 0x0940 {
+            let _level = self.u8()?;
+        }
+
+        let flags = self.u32()?;
+        if 0 != flags & F_H_FILTER {
+            return Err(invalid("unsupported lzop filter"));
+        }
+
+        let _mode = self.u32()?;
+        let _mtime_low = self.u32()?;
+        if version >= 0x0940 {
+            let _mtime_high = self.u32()?;
+        }
+
+        let name_len = usize::from(self.u8()?);
+        self.skip(name_len)?;
+        let _header_checksum = self.u32()?;
+
+        if 0 != flags & F_H_EXTRA_FIELD {
+            let extra_len = self.u32()? as usize;
+            self.skip(extra_len)?;
+            let _extra_checksum = self.u32()?;
+        }
+
+        Ok(flags)
+    }
+
+    /// `false` at the end-of-file marker.
+    fn read_block(&mut self, flags: u32) -> io::Result<bool> {
+        let dst_len = self.u32()? as usize;
+        if 0 == dst_len {
+            return Ok(false);
+        }
+
+        let src_len = self.u32()? as usize;
+        if dst_len > MAX_BLOCK || src_len > dst_len {
+            return Err(invalid("bad lzop block size"));
+        }
+
+        let mut checksums = 0;
+        if 0 != flags & F_ADLER32_D {
+            checksums += 1;
+        }
+        if 0 != flags & F_CRC32_D {
+            checksums += 1;
+        }
+        if src_len < dst_len {
+            if 0 != flags & F_ADLER32_C {
+                checksums += 1;
+            }
+            if 0 != flags & F_CRC32_C {
+                checksums += 1;
+            }
+        }
+        self.skip(4 * checksums)?;
+
+        let mut src = vec![0u8; src_len];
+        self.inner.read_exact(&mut src)?;
+
+        self.block = if src_len == dst_len {
+            src
+        } else {
+            lzo1x_decompress(&src, dst_len)?
+        };
+        self.pos = 0;
+        Ok(true)
+    }
+
+    fn skip(&mut self, len: usize) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.inner).take(len as u64), &mut io::sink())?;
+        if skipped != len as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let mut buf = [0u8; 2];
+        self.inner.read_exact(&mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.inner.read_exact(&mut buf)?;
+        Ok(u32::from_be_bytes(buf))
+    }
+}
+
+impl<R: Read> Read for LzopDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let flags = match self.flags {
+            Some(flags) => flags,
+            None => {
+                let flags = self.read_header()?;
+                self.flags = Some(flags);
+                flags
+            }
+        };
+
+        while self.pos == self.block.len() {
+            if self.done || !self.read_block(flags)? {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.block.len() - self.pos);
+        buf[..len].copy_from_slice(&self.block[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// LZO1X, as `lzo1x_decompress_safe`, into exactly `dst_len` bytes.
+fn lzo1x_decompress(src: &[u8], dst_len: usize) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(dst_len);
+    let mut ip = 0;
+
+    let byte = |ip: &mut usize| -> io::Result<usize> {
+        let byte = *src.get(*ip).ok_or_else(corrupt)?;
+        *ip += 1;
+        Ok(usize::from(byte))
+    };
+
+    // a zero length byte is followed by further 255s, as zeros, then the rest
+    let run = |ip: &mut usize, base: usize| -> io::Result<usize> {
+        let mut len = base;
+        loop {
+            match byte(ip)? {
+                0 => len += 255,
+                rest => return Ok(len + rest),
+            }
+        }
+    };
+
+    let literals = |ip: &mut usize, out: &mut Vec<u8>, len: usize| -> io::Result<()> {
+        let from = src.get(*ip..*ip + len).ok_or_else(corrupt)?;
+        if out.len() + len > dst_len {
+            return Err(corrupt());
+        }
+        out.extend_from_slice(from);
+        *ip += len;
+        Ok(())
+    };
+
+    // literals copied since the last match: 0-3, or 4 meaning "more than three"
+    let mut state;
+
+    if src.first().is_some_and(|&first| first > 17) {
+        let len = byte(&mut ip)? - 17;
+        literals(&mut ip, &mut out, len)?;
+        state = if len < 4 { len } else { 4 };
+    } else {
+        state = 0;
+    }
+
+    loop {
+        let t = byte(&mut ip)?;
+        let (distance, len, next);
+
+        if t < 16 {
+            if 0 == state {
+                let len = if 0 == t { run(&mut ip, 15)? } else { t };
+                literals(&mut ip, &mut out, len + 3)?;
+                state = 4;
+                continue;
+            }
+
+            let low = byte(&mut ip)?;
+            next = t & 3;
+            if 4 == state {
+                distance = 1 + 0x0800 + (t >> 2) + (low << 2);
+                len = 3;
+            } else {
+                distance = 1 + (t >> 2) + (low << 2);
+                len = 2;
+            }
+        } else if t >= 64 {
+            let low = byte(&mut ip)?;
+            next = t & 3;
+            distance = 1 + ((t >> 2) & 7) + (low << 3);
+            len = (t >> 5) + 1;
+        } else if t >= 32 {
+            len = match t & 31 {
+                0 => run(&mut ip, 31)?,
+                len => len,
+            } + 2;
+            let le = byte(&mut ip)? | (byte(&mut ip)? << 8);
+            next = le & 3;
+            distance = 1 + (le >> 2);
+        } else {
+            let high = (t & 8) << 11;
+            len = match t & 7 {
+                0 => run(&mut ip, 7)?,
+                len => len,
+            } + 2;
+            let le = byte(&mut ip)? | (byte(&mut ip)? << 8);
+            next = le & 3;
+            if 0 == high && 0 == le >> 2 {
+                break;
+            }
+            distance = high + (le >> 2) + 0x4000;
+        }
+
+        if distance > out.len() || out.len() + len > dst_len {
+            return Err(corrupt());
+        }
+        let start = out.len() - distance;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+
+        literals(&mut ip, &mut out, next)?;
+        state = next;
+    }
+
+    if out.len() != dst_len {
+        return Err(corrupt());
+    }
+
+    Ok(out)
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(message: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn corrupt() -> io::Error {
+    invalid("corrupt lzo data")
+}
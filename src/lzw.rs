@@ -0,0 +1,202 @@
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+
+const CLEAR: u32 = 256;
+const INIT_BITS: u32 = 9;
+
+/// Decodes the LZW of Unix `compress`, i.e. `.Z` files, as `ncompress` does.
+///
+/// The encoder writes codes in groups of eight, and whenever the code width changes
+/// (it grows, or the table is cleared), the rest of the current group is padding.
+pub struct LzwDecoder<R> {
+    inner: R,
+    max_bits: u32,
+    block_mode: bool,
+    n_bits: u32,
+    max_code: u32,
+    free_ent: u32,
+    prefix: Vec<u16>,
+    suffix: Vec<u8>,
+    old_code: Option<u32>,
+    fin_char: u8,
+    bits: u64,
+    bit_count: u32,
+    /// Codes read at the current width, mod 8.
+    group: u32,
+    /// The decoded string, reversed, still to be returned.
+    pending: Vec<u8>,
+    started: bool,
+}
+
+impl<R: BufRead> LzwDecoder<R> {
+    pub fn new(inner: R) -> LzwDecoder<R> {
+        LzwDecoder {
+            inner,
+            max_bits: 16,
+            block_mode: true,
+            n_bits: INIT_BITS,
+            max_code: (1 << INIT_BITS) - 1,
+            free_ent: 0,
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+            old_code: None,
+            fin_char: 0,
+            bits: 0,
+            bit_count: 0,
+            group: 0,
+            pending: Vec::new(),
+            started: false,
+        }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 3];
+        self.inner.read_exact(&mut header)?;
+        if [0x1f, 0x9d] != header[..2] {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not compress'd"));
+        }
+
+        self.max_bits = u32::from(header[2] & 0x1f);
+        if !(INIT_BITS..=16).contains(&self.max_bits) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported compress code width: {}", self.max_bits),
+            ));
+        }
+
+        self.block_mode = 0 != header[2] & 0x80;
+        self.free_ent = if self.block_mode { CLEAR + 1 } else { CLEAR };
+        self.prefix = vec![0; 1 << self.max_bits];
+        self.suffix = (0..1u32 << self.max_bits).map(|c| c as u8).collect();
+        self.started = true;
+        Ok(())
+    }
+
+    /// `None` at the end of the input; a partial code there is ignored.
+    fn read_code(&mut self) -> io::Result<Option<u32>> {
+        while self.bit_count < self.n_bits {
+            let byte = match self.inner.fill_buf()?.first() {
+                Some(&byte) => byte,
+                None => return Ok(None),
+            };
+            self.inner.consume(1);
+            self.bits |= u64::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        let code = (self.bits & ((1 << self.n_bits) - 1)) as u32;
+        self.bits >>= self.n_bits;
+        self.bit_count -= self.n_bits;
+        self.group = (self.group + 1) % 8;
+        Ok(Some(code))
+    }
+
+    /// Skip the padding at the end of the current group.
+    fn end_group(&mut self) -> io::Result<()> {
+        while 0 != self.group {
+            if self.read_code()?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode the next code's string into `pending`; `false` at the end.
+    fn next_string(&mut self) -> io::Result<bool> {
+        loop {
+            if self.free_ent > self.max_code && self.n_bits < self.max_bits {
+                self.end_group()?;
+                self.n_bits += 1;
+                self.max_code = if self.n_bits == self.max_bits {
+                    1 << self.max_bits
+                } else {
+                    (1 << self.n_bits) - 1
+                };
+            }
+
+            let mut code = match self.read_code()? {
+                Some(code) => code,
+                None => return Ok(false),
+            };
+
+            let old_code = match self.old_code {
+                Some(old_code) => old_code,
+                None => {
+                    if code >= CLEAR {
+                        return Err(corrupt());
+                    }
+                    self.fin_char = code as u8;
+                    self.old_code = Some(code);
+                    self.pending.push(self.fin_char);
+                    return Ok(true);
+                }
+            };
+
+            if CLEAR == code && self.block_mode {
+                self.end_group()?;
+                self.free_ent = CLEAR;
+                self.n_bits = INIT_BITS;
+                self.max_code = (1 << INIT_BITS) - 1;
+                continue;
+            }
+
+            let in_code = code;
+
+            // the code being defined right now: KwKwK
+            if code >= self.free_ent {
+                if code > self.free_ent {
+                    return Err(corrupt());
+                }
+                self.pending.push(self.fin_char);
+                code = old_code;
+            }
+
+            while code >= CLEAR {
+                self.pending.push(self.suffix[code as usize]);
+                code = u32::from(self.prefix[code as usize]);
+            }
+
+            self.fin_char = code as u8;
+            self.pending.push(self.fin_char);
+
+            if self.free_ent < 1 << self.max_bits {
+                self.prefix[self.free_ent as usize] = old_code as u16;
+                self.suffix[self.free_ent as usize] = self.fin_char;
+                self.free_ent += 1;
+            }
+
+            self.old_code = Some(in_code);
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: BufRead> Read for LzwDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.started {
+            self.start()?;
+        }
+
+        let mut written = 0;
+        while written < buf.len() {
+            match self.pending.pop() {
+                Some(byte) => {
+                    buf[written] = byte;
+                    written += 1;
+                }
+                None => {
+                    if !self.next_string()? {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "corrupt compress'd data")
+}
@@ -1,14 +1,12 @@
 use std::borrow;
+use std::convert::TryFrom;
 use std::fs;
 use std::path::Path;
 
-use ar;
 use failure::err_msg;
 use failure::Error;
-use flate2;
-use tar;
-use zip;
 
+use crate::iso9660;
 use crate::simple_time;
 
 #[derive(Clone, Debug)]
@@ -16,6 +14,7 @@ pub struct Meta {
     pub mtime: u64,
     pub item_type: ItemType,
     pub ownership: Ownership,
+    pub encryption: Option<Encryption>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -40,6 +39,14 @@ pub enum ItemType {
     },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encryption {
+    /// The traditional PKWARE stream cipher.
+    ZipCrypto,
+    /// WinZip's AE-1/AE-2.
+    Aes,
+}
+
 enum RawItemType {
     Sloppy,
     SymbolicLink,
@@ -117,6 +124,7 @@ pub fn just_stream() -> Meta {
         mtime: 0,
         item_type: ItemType::RegularFile,
         ownership: Ownership::Unknown,
+        encryption: None,
     }
 }
 
@@ -144,9 +152,66 @@ pub fn file<P: AsRef<Path>>(path: P) -> Result<Meta, Error> {
         mtime: simple_time::simple_time_sys(meta.modified()?),
         item_type,
         ownership: Ownership::Unknown,
+        encryption: None,
+    })
+}
+
+pub fn dir<P: AsRef<Path>>(path: P) -> Result<Meta, Error> {
+    Ok(Meta {
+        mtime: simple_time::simple_time_sys(path.as_ref().metadata()?.modified()?),
+        item_type: ItemType::Directory,
+        ownership: Ownership::Unknown,
+        encryption: None,
     })
 }
 
+/// A git tree entry for a file or symlink, which has only a mode; the time is the commit's.
+pub fn for_git(mode: u32, committed: u64, link: Option<&[u8]>) -> Meta {
+    Meta {
+        mtime: simple_time::simple_time_epoch_seconds(committed),
+        item_type: match link {
+            Some(link) => ItemType::SymbolicLink(link.to_vec().into_boxed_slice()),
+            None => ItemType::RegularFile,
+        },
+        ownership: Ownership::Posix {
+            user: None,
+            group: None,
+            mode,
+        },
+        encryption: None,
+    }
+}
+
+pub fn for_iso(record: &iso9660::Record) -> Meta {
+    Meta {
+        mtime: simple_time::simple_time_epoch_seconds(record.mtime),
+        item_type: match record.link {
+            Some(ref link) => ItemType::SymbolicLink(link.clone().into_boxed_slice()),
+            None if record.is_dir() => ItemType::Directory,
+            None => {
+                let mode = record.posix.map(|(mode, _, _)| mode).unwrap_or(0);
+                let (major, minor) = record.device.unwrap_or((0, 0));
+                match (mode >> 12) & 0b1111 {
+                    S_IFIFO => ItemType::Fifo,
+                    S_IFSOCK => ItemType::Socket,
+                    S_IFCHR => ItemType::CharacterDevice { major, minor },
+                    S_IFBLK => ItemType::BlockDevice { major, minor },
+                    _ => ItemType::RegularFile,
+                }
+            }
+        },
+        ownership: match record.posix {
+            Some((mode, uid, gid)) => Ownership::Posix {
+                user: Some(PosixEntity::just_id(u64::from(uid))),
+                group: Some(PosixEntity::just_id(u64::from(gid))),
+                mode,
+            },
+            None => Ownership::Unknown,
+        },
+        encryption: None,
+    }
+}
+
 pub fn for_ar(header: &ar::Header) -> Result<Meta, Error> {
     Ok(Meta {
         mtime: simple_time::simple_time_epoch_seconds(header.mtime()),
@@ -156,6 +221,7 @@ pub fn for_ar(header: &ar::Header) -> Result<Meta, Error> {
             group: Some(PosixEntity::just_id(u64::from(header.gid()))),
             mode: header.mode(),
         },
+        encryption: None,
     })
 }
 
@@ -164,6 +230,7 @@ pub fn gz(header: &flate2::GzHeader) -> Result<Meta, Error> {
         mtime: simple_time::simple_time_epoch_seconds(u64::from(header.mtime())),
         item_type: ItemType::RegularFile,
         ownership: Ownership::Unknown,
+        encryption: None,
     })
 }
 
@@ -174,7 +241,12 @@ pub fn for_tar(
     let mode = header.mode()?;
     Ok(Meta {
         mtime: simple_time::simple_time_epoch_seconds(header.mtime().unwrap_or(0)),
-        item_type: match RawItemType::from_mode_lossy(mode) {
+        item_type: match match header.entry_type() {
+            tar::EntryType::Symlink => RawItemType::SymbolicLink,
+            tar::EntryType::Char => RawItemType::CharacterDevice,
+            tar::EntryType::Block => RawItemType::BlockDevice,
+            _ => RawItemType::from_mode_lossy(mode),
+        } {
             RawItemType::SymbolicLink => ItemType::SymbolicLink(
                 link_name_bytes
                     .ok_or(err_msg("symbolic-link style file with no link"))?
@@ -197,7 +269,15 @@ pub fn for_tar(
                     .device_minor()?
                     .ok_or(err_msg("block device without minor"))?,
             },
-            RawItemType::Sloppy => ItemType::from_mode_lossy(mode),
+            // many tars don't bother with the type bits in the mode
+            RawItemType::Sloppy => match header.entry_type() {
+                tar::EntryType::Regular | tar::EntryType::Continuous => ItemType::RegularFile,
+                // given the content of what it links to, by `unpack_tar`
+                tar::EntryType::Link => ItemType::RegularFile,
+                tar::EntryType::Directory => ItemType::Directory,
+                tar::EntryType::Fifo => ItemType::Fifo,
+                _ => ItemType::from_mode_lossy(mode),
+            },
         },
         ownership: Ownership::Posix {
             user: Some(PosixEntity {
@@ -210,12 +290,18 @@ pub fn for_tar(
             }),
             mode: header.mode()?,
         },
+        encryption: None,
     })
 }
 
 pub fn for_zip(header: &zip::read::ZipFile) -> Result<Meta, Error> {
     Ok(Meta {
-        mtime: simple_time::simple_time_tm(header.last_modified().to_time()),
+        mtime: header
+            .last_modified()
+            .to_time()
+            .ok()
+            .and_then(|time| u64::try_from(time.unix_timestamp_nanos()).ok())
+            .unwrap_or(0),
         item_type: if header.name_raw().ends_with(b"/") {
             ItemType::Directory
         } else {
@@ -230,5 +316,27 @@ pub fn for_zip(header: &zip::read::ZipFile) -> Result<Meta, Error> {
         } else {
             Ownership::Unknown
         },
+        encryption: if zip_extra_fields(header.extra_data()).any(|(id, _)| 0x9901 == id) {
+            Some(Encryption::Aes)
+        } else {
+            None
+        },
+    })
+}
+
+/// The (id, data) pairs of a zip "extra field" block; stops at the first malformed record.
+pub fn zip_extra_fields(mut extra: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if extra.len() < 4 {
+            return None;
+        }
+        let id = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = usize::from(u16::from_le_bytes([extra[2], extra[3]]));
+        if extra.len() < 4 + len {
+            return None;
+        }
+        let data = &extra[4..4 + len];
+        extra = &extra[4 + len..];
+        Some((id, data))
     })
 }
@@ -1,6 +1,8 @@
 use std::fs;
 use std::io;
 use std::io::BufRead;
+use std::io::Read;
+use std::io::Seek;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -27,6 +29,25 @@ impl Mio {
     pub fn header(&mut self) -> Vec<u8> {
         fill_buf(&mut self.inner).expect("mio: filling")
     }
+
+    /// Up to the last `len` bytes of the file, independent of the current position.
+    pub fn tail(&self, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(&self.path)?;
+        let size = file.metadata()?.len();
+        file.seek(io::SeekFrom::Start(size.saturating_sub(len)))?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Up to `len` bytes from `offset`, independent of the current position.
+    pub fn at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(io::SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        file.take(len as u64).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
 }
 
 impl Clone for Mio {
@@ -0,0 +1,58 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::package;
+use crate::unpacker::Entry;
+
+/// What an npm package tarball's `package/package.json` says.
+#[derive(Clone, Debug, Default)]
+pub struct NpmInfo {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    /// An SPDX expression, or the `type` of an old-style licence object.
+    pub license: String,
+    pub homepage: String,
+    /// `(name, version range)`
+    pub dependencies: Vec<(String, String)>,
+    pub dev_dependencies: Vec<(String, String)>,
+    pub peer_dependencies: Vec<(String, String)>,
+    pub optional_dependencies: Vec<(String, String)>,
+}
+
+pub fn identify(children: &[Entry]) -> Option<NpmInfo> {
+    parse_package_json(package::read_child(children, b"package/package.json")?)
+}
+
+pub fn parse_package_json<R: Read>(from: R) -> Option<NpmInfo> {
+    let json: Value = serde_json::from_reader(from).ok()?;
+    let string = |key: &str| json[key].as_str().unwrap_or("").to_string();
+    let dependencies = |key: &str| match json[key].as_object() {
+        Some(map) => map
+            .iter()
+            .map(|(name, range)| (name.clone(), range.as_str().unwrap_or("").to_string()))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Some(NpmInfo {
+        name: string("name"),
+        version: string("version"),
+        description: string("description"),
+        license: match json["license"] {
+            Value::String(ref license) => license.clone(),
+            Value::Object(ref license) => license
+                .get("type")
+                .and_then(|kind| kind.as_str())
+                .unwrap_or("")
+                .to_string(),
+            _ => String::new(),
+        },
+        homepage: string("homepage"),
+        dependencies: dependencies("dependencies"),
+        dev_dependencies: dependencies("devDependencies"),
+        peer_dependencies: dependencies("peerDependencies"),
+        optional_dependencies: dependencies("optionalDependencies"),
+    })
+}
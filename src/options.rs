@@ -0,0 +1,42 @@
+use crate::git::GitView;
+
+/// Called with the path of an encrypted item; returns a password to try, if any.
+pub type PasswordProvider = dyn Fn(&[u8]) -> Option<Vec<u8>>;
+
+pub struct Options {
+    /// Used to decrypt encrypted zip members; both ZipCrypto and AES are supported.
+    pub password: Option<Box<PasswordProvider>>,
+
+    /// Keep what was read from a damaged archive, instead of reporting only the error.
+    pub salvage: bool,
+
+    /// Hash every file's content, before it's thrown away for having been unpacked.
+    pub digests: bool,
+
+    /// Look for licence tags, notices and texts in every text file; see `license_summary`.
+    pub licenses: bool,
+
+    /// Present a Debian `.dsc`, and the files it lists, as one unpacked source tree.
+    pub debian_source: bool,
+
+    /// Inflate files which are a bare zlib stream; on by default. Finding them costs a trial
+    /// inflate of files with a plausible header, and unpacking them, e.g. every git object.
+    pub zlib_streams: bool,
+
+    /// How to present git repositories found in a directory; by default, the tree of `HEAD`.
+    pub git: GitView,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            password: None,
+            salvage: false,
+            digests: false,
+            licenses: false,
+            debian_source: false,
+            zlib_streams: true,
+            git: GitView::Tree("HEAD".to_string()),
+        }
+    }
+}
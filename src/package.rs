@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::apk;
+use crate::deb;
+use crate::digest::Digests;
+use crate::gem;
+use crate::java;
+use crate::meta::ItemType;
+use crate::npm;
+use crate::python;
+use crate::unpacker::Entry;
+use crate::unpacker::Status;
+
+/// Package metadata found inside an archive, from the archive's own control files.
+#[derive(Clone, Debug)]
+pub enum Package {
+    Apk(apk::ApkInfo),
+    Deb(deb::DebInfo),
+    Python(python::PythonInfo),
+    Java(java::JavaInfo),
+    Npm(npm::NpmInfo),
+    Gem(gem::GemInfo),
+}
+
+/// Try each package format against what an archive unpacked to.
+pub fn identify(children: &[Entry]) -> Option<Package> {
+    if let Some(info) = apk::identify(children) {
+        return Some(Package::Apk(info));
+    }
+
+    if let Some(info) = deb::identify(children) {
+        return Some(Package::Deb(info));
+    }
+
+    if let Some(info) = python::identify(children) {
+        return Some(Package::Python(info));
+    }
+
+    if let Some(info) = java::identify(children) {
+        return Some(Package::Java(info));
+    }
+
+    if let Some(info) = npm::identify(children) {
+        return Some(Package::Npm(info));
+    }
+
+    if let Some(info) = gem::identify(children) {
+        return Some(Package::Gem(info));
+    }
+
+    None
+}
+
+/// The direct child with this path (ignoring any leading `./`), if any.
+pub fn child<'e>(entries: &'e [Entry], path: &[u8]) -> Option<&'e Entry> {
+    entries.iter().find(|entry| {
+        path == entry
+            .local
+            .path
+            .strip_prefix(b"./")
+            .unwrap_or(&entry.local.path)
+    })
+}
+
+/// The content of a direct child, if it's a regular file we still have a temp for.
+pub fn read_child(entries: &[Entry], path: &[u8]) -> Option<fs::File> {
+    fs::File::open(child(entries, path)?.local.temp.as_ref()?).ok()
+}
+
+/// The result of checking a package's own list of hashes against what it unpacked to.
+/// Paths are as in the list: relative, without a leading `./`.
+#[derive(Clone, Debug, Default)]
+pub struct Verification {
+    /// Listed, present, but with different content.
+    pub mismatched: Vec<String>,
+    /// Listed, but not present as a regular file.
+    pub missing: Vec<String>,
+    /// Regular files which aren't listed.
+    pub extra: Vec<String>,
+    /// Listed and present, but we no longer had the content, as it was unpacked without
+    /// `Options::digests`, or we don't know the hash algorithm.
+    pub unchecked: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// The regular files in a list of entries, by their path without any leading `./`.
+pub fn regular_files(entries: &[Entry]) -> HashMap<String, &Entry> {
+    entries
+        .iter()
+        .filter(|entry| ItemType::RegularFile == entry.local.meta.item_type)
+        .map(|entry| {
+            let path = String::from_utf8_lossy(&entry.local.path);
+            (path.trim_start_matches("./").to_string(), entry)
+        })
+        .collect()
+}
+
+/// The digests of an entry's content, if they were recorded, or it's still available.
+/// See `Options::digests`.
+pub fn content_digests(entry: &Entry) -> Option<Digests> {
+    match (&entry.digests, &entry.local.temp, &entry.children) {
+        (Some(digests), _, _) => Some(digests.clone()),
+        (None, Some(temp), _) => Digests::of_path(temp).ok(),
+        (None, None, Status::Unnecessary) => Some(Digests::empty()),
+        (None, None, _) => None,
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+
+use crate::deb;
+use crate::digest;
+use crate::package;
+use crate::package::Package;
+use crate::package::Verification;
+use crate::unpacker::Entry;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PythonKind {
+    /// A zip with a `*.dist-info/` directory.
+    Wheel,
+    /// A tar whose single top-level directory contains `PKG-INFO`.
+    Sdist,
+    /// A zip with an `EGG-INFO/` directory.
+    Egg,
+}
+
+/// The core metadata of a Python distribution, from `METADATA` or `PKG-INFO`.
+#[derive(Clone, Debug)]
+pub struct PythonInfo {
+    pub kind: PythonKind,
+    pub name: String,
+    pub version: String,
+    pub summary: String,
+    pub home_page: String,
+    pub author: String,
+    pub author_email: String,
+    pub license: String,
+    pub requires_python: String,
+    /// `Requires-Dist`, which may be repeated.
+    pub requires_dist: Vec<String>,
+    /// `Classifier`, which may be repeated.
+    pub classifiers: Vec<String>,
+    /// `Tag`s from a wheel's `WHEEL` file, e.g. `py3-none-any`.
+    pub tags: Vec<String>,
+    /// The directory holding the metadata, e.g. `foo-1.0.dist-info`.
+    pub metadata_dir: String,
+    /// Every field, in file order, including those we don't otherwise understand.
+    pub fields: Vec<(String, String)>,
+}
+
+pub fn identify(children: &[Entry]) -> Option<PythonInfo> {
+    for entry in children {
+        let path = String::from_utf8_lossy(&entry.local.path);
+        let path = path.trim_start_matches("./");
+
+        let (kind, dir) = if let Some(dir) = path.strip_suffix("/METADATA") {
+            if !dir.ends_with(".dist-info") || dir.contains('/') {
+                continue;
+            }
+            (PythonKind::Wheel, dir)
+        } else if "EGG-INFO/PKG-INFO" == path {
+            (PythonKind::Egg, "EGG-INFO")
+        } else if let Some(dir) = path.strip_suffix("/PKG-INFO") {
+            if dir.contains('/') || !is_only_top_dir(children, dir) {
+                continue;
+            }
+            (PythonKind::Sdist, dir)
+        } else {
+            continue;
+        };
+
+        let metadata = match entry.local.temp.as_ref().map(fs::File::open) {
+            Some(Ok(metadata)) => metadata,
+            _ => continue,
+        };
+        let mut info = parse_metadata(kind, metadata);
+        info.metadata_dir = dir.to_string();
+
+        if PythonKind::Wheel == kind {
+            if let Some(wheel) = package::read_child(children, format!("{}/WHEEL", dir).as_bytes())
+            {
+                info.tags = deb::paragraphs(wheel)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(key, _)| "Tag" == key)
+                    .map(|(_, value)| value)
+                    .collect();
+            }
+        }
+
+        return Some(info);
+    }
+
+    None
+}
+
+/// Whether every entry is `dir`, or inside it, as in an sdist; not just any `*/PKG-INFO`.
+fn is_only_top_dir(children: &[Entry], dir: &str) -> bool {
+    children.iter().all(|entry| {
+        let path = String::from_utf8_lossy(&entry.local.path);
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        path == dir
+            || path
+                .strip_prefix(dir)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Parse the email-header style core metadata; the body, if any, is ignored.
+pub fn parse_metadata<R: std::io::Read>(kind: PythonKind, from: R) -> PythonInfo {
+    let mut info = PythonInfo {
+        kind,
+        name: String::new(),
+        version: String::new(),
+        summary: String::new(),
+        home_page: String::new(),
+        author: String::new(),
+        author_email: String::new(),
+        license: String::new(),
+        requires_python: String::new(),
+        requires_dist: Vec::new(),
+        classifiers: Vec::new(),
+        tags: Vec::new(),
+        metadata_dir: String::new(),
+        fields: Vec::new(),
+    };
+
+    let fields = deb::paragraphs(from).into_iter().next().unwrap_or_default();
+
+    for (key, value) in &fields {
+        let value = value.clone();
+        match key.to_ascii_lowercase().as_str() {
+            "name" => info.name = value,
+            "version" => info.version = value,
+            "summary" => info.summary = value,
+            "home-page" => info.home_page = value,
+            "author" => info.author = value,
+            "author-email" => info.author_email = value,
+            "license" => info.license = value,
+            "requires-python" => info.requires_python = value,
+            "requires-dist" => info.requires_dist.push(value),
+            "classifier" => info.classifiers.push(value),
+            _ => (),
+        }
+    }
+
+    info.fields = fields;
+    info
+}
+
+/// Check a wheel's `RECORD` against its members.
+/// `None` if the entry isn't a wheel, or the wheel has no `RECORD`.
+pub fn verify_record(wheel: &Entry) -> Option<Verification> {
+    let info = match wheel.package {
+        Some(Package::Python(ref info)) if PythonKind::Wheel == info.kind => info,
+        _ => return None,
+    };
+
+    let children = wheel.children.entries();
+    let record_path = format!("{}/RECORD", info.metadata_dir);
+    let record = package::read_child(children, record_path.as_bytes())?;
+
+    let mut files = package::regular_files(children);
+    let mut report = Verification::default();
+
+    for line in deb::lines(record) {
+        let fields = csv_fields(&line);
+        if fields.len() < 2 || fields[0].is_empty() {
+            continue;
+        }
+
+        let path = &fields[0];
+        let entry = files.remove(path);
+        if fields[1].is_empty() {
+            // the RECORD itself, and anything else deliberately unhashed
+            continue;
+        }
+
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                report.missing.push(path.clone());
+                continue;
+            }
+        };
+
+        let mut parts = fields[1].splitn(2, '=');
+        let algorithm = parts.next().unwrap_or("");
+        let expected = parts.next().unwrap_or("");
+
+        let actual = package::content_digests(entry).and_then(|digests| match algorithm {
+            "sha256" => Some(digest::base64_url(&digests.sha256)),
+            "sha1" => Some(digest::base64_url(&digests.sha1)),
+            "md5" => Some(digest::base64_url(&digests.md5)),
+            _ => None,
+        });
+
+        match actual {
+            Some(ref actual) if actual == expected => (),
+            Some(_) => report.mismatched.push(path.clone()),
+            None => report.unchecked.push(path.clone()),
+        }
+    }
+
+    report.extra = files
+        .into_keys()
+        .filter(|path| {
+            *path != format!("{}/RECORD.jws", info.metadata_dir)
+                && *path != format!("{}/RECORD.p7s", info.metadata_dir)
+        })
+        .collect();
+    report.extra.sort();
+
+    Some(report)
+}
+
+/// Split a line of CSV, handling `"`-quoting.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && Some(&'"') == chars.peek() => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+
+    fields.push(current);
+    fields
+}
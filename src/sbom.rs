@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use serde_json::json;
+use serde_json::Value;
+use sha2::Digest;
+
+use crate::digest;
+use crate::digest::Digests;
+use crate::meta::ItemType;
+use crate::package;
+use crate::package::Package;
+use crate::simple_time;
+use crate::unpacker::Entry;
+
+/// Something in the tree worth listing: a package, or a plain file.
+struct Node {
+    parent: Option<usize>,
+    /// From the root, through each archive, joined with `/`.
+    path: String,
+    component: Option<Component>,
+    digests: Option<Digests>,
+}
+
+/// The common subset of every package format's metadata.
+#[derive(Default)]
+struct Component {
+    name: String,
+    version: String,
+    purl: Option<String>,
+    license: String,
+}
+
+/// An SPDX 2.3 JSON document for everything below `entries`, as one package called `name`.
+pub fn spdx(entries: &[Entry], name: &str) -> String {
+    let (root, nodes) = nodes(entries, name);
+
+    let mut packages = vec![spdx_package(&root, "SPDXRef-Root", None, None)];
+    let mut files = Vec::new();
+    let mut relationships = vec![relationship(
+        "SPDXRef-DOCUMENT",
+        "DESCRIBES",
+        "SPDXRef-Root",
+    )];
+
+    for (id, node) in nodes.iter().enumerate() {
+        match node.component {
+            Some(ref component) => packages.push(spdx_package(
+                component,
+                &spdx_id(&nodes, id),
+                Some(&node.path),
+                node.digests.as_ref(),
+            )),
+            None => {
+                let mut file = json!({
+                    "SPDXID": spdx_id(&nodes, id),
+                    "fileName": format!("./{}", node.path),
+                });
+                if let Some(ref digests) = node.digests {
+                    file["checksums"] = spdx_checksums(digests);
+                }
+                files.push(file);
+            }
+        }
+
+        let parent = match node.parent {
+            Some(parent) => spdx_id(&nodes, parent),
+            None => "SPDXRef-Root".to_string(),
+        };
+        relationships.push(relationship(&parent, "CONTAINS", &spdx_id(&nodes, id)));
+    }
+
+    let mut namespace = sha2::Sha256::new();
+    for node in &nodes {
+        namespace.update(node.path.as_bytes());
+        if let Some(ref digests) = node.digests {
+            namespace.update(digests.sha256);
+        }
+    }
+
+    let document = json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}",
+            percent_encode(name),
+            digest::hex(&namespace.finalize())
+        ),
+        "creationInfo": {
+            "created": now(),
+            "creators": [format!("Tool: splayers-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "files": files,
+        "relationships": relationships,
+    });
+
+    serde_json::to_string_pretty(&document).expect("serialising a Value")
+}
+
+/// A CycloneDX 1.5 JSON BOM for everything below `entries`, with `name` as the subject.
+/// Containment is expressed by nesting components.
+pub fn cyclonedx(entries: &[Entry], name: &str) -> String {
+    let (root, nodes) = nodes(entries, name);
+
+    let mut root = cyclonedx_component(&root, "root", None, None);
+    root["type"] = json!("application");
+
+    let document = json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": now(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "splayers",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": root,
+        },
+        "components": cyclonedx_children(&nodes, &children(&nodes), None),
+    });
+
+    serde_json::to_string_pretty(&document).expect("serialising a Value")
+}
+
+fn nodes(entries: &[Entry], name: &str) -> (Component, Vec<Node>) {
+    let root = match package::identify(entries) {
+        Some(ref package) => component(package),
+        None => Component {
+            name: name.to_string(),
+            ..Component::default()
+        },
+    };
+
+    let mut nodes = Vec::new();
+    walk(entries, None, "", &mut nodes);
+    (root, nodes)
+}
+
+/// Packages, and regular files that aren't packages, are nodes; anything else, e.g. a
+/// directory, is skipped, with its children belonging to the nearest node above.
+fn walk(entries: &[Entry], parent: Option<usize>, prefix: &str, nodes: &mut Vec<Node>) {
+    for entry in entries {
+        let path = String::from_utf8_lossy(&entry.local.path);
+        let path = format!(
+            "{}{}",
+            prefix,
+            path.trim_start_matches("./").trim_end_matches('/')
+        );
+
+        let component = entry.package.as_ref().map(component);
+        let mut here = parent;
+
+        if component.is_some() || ItemType::RegularFile == entry.local.meta.item_type {
+            nodes.push(Node {
+                parent,
+                path: path.clone(),
+                component,
+                digests: entry.digests.clone(),
+            });
+            here = Some(nodes.len() - 1);
+        }
+
+        walk(entry.children.entries(), here, &format!("{}/", path), nodes);
+    }
+}
+
+fn component(package: &Package) -> Component {
+    match *package {
+        Package::Apk(ref info) => Component {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            purl: Some(format!(
+                "pkg:apk/alpine/{}@{}?arch={}",
+                percent_encode(&info.name),
+                percent_encode(&info.version),
+                percent_encode(&info.arch)
+            )),
+            license: info.license.clone(),
+        },
+        Package::Deb(ref info) => Component {
+            name: info.control.package.clone(),
+            version: info.control.version.clone(),
+            purl: Some(format!(
+                "pkg:deb/debian/{}@{}?arch={}",
+                percent_encode(&info.control.package),
+                percent_encode(&info.control.version),
+                percent_encode(&info.control.architecture)
+            )),
+            license: String::new(),
+        },
+        Package::Python(ref info) => Component {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            purl: Some(format!(
+                "pkg:pypi/{}@{}",
+                percent_encode(&info.name.to_ascii_lowercase().replace('_', "-")),
+                percent_encode(&info.version)
+            )),
+            license: info.license.clone(),
+        },
+        Package::Java(ref info) => match info.maven.first() {
+            Some(maven) => Component {
+                name: maven.artifact_id.clone(),
+                version: maven.version.clone(),
+                purl: Some(format!(
+                    "pkg:maven/{}/{}@{}",
+                    percent_encode(&maven.group_id),
+                    percent_encode(&maven.artifact_id),
+                    percent_encode(&maven.version)
+                )),
+                license: info.attribute("Bundle-License").unwrap_or("").to_string(),
+            },
+            None => Component {
+                name: [
+                    "Implementation-Title",
+                    "Bundle-SymbolicName",
+                    "Automatic-Module-Name",
+                ]
+                .iter()
+                .find_map(|name| info.attribute(name))
+                .unwrap_or("")
+                .to_string(),
+                version: ["Implementation-Version", "Bundle-Version"]
+                    .iter()
+                    .find_map(|name| info.attribute(name))
+                    .unwrap_or("")
+                    .to_string(),
+                purl: None,
+                license: info.attribute("Bundle-License").unwrap_or("").to_string(),
+            },
+        },
+        Package::Npm(ref info) => Component {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            purl: Some(format!(
+                "pkg:npm/{}@{}",
+                info.name
+                    .split('/')
+                    .map(percent_encode)
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                percent_encode(&info.version)
+            )),
+            license: info.license.clone(),
+        },
+        Package::Gem(ref info) => Component {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            purl: Some(format!(
+                "pkg:gem/{}@{}",
+                percent_encode(&info.name),
+                percent_encode(&info.version)
+            )),
+            license: info.licenses.join(" OR "),
+        },
+    }
+}
+
+fn spdx_id(nodes: &[Node], id: usize) -> String {
+    match nodes[id].component {
+        Some(_) => format!("SPDXRef-Package-{}", id),
+        None => format!("SPDXRef-File-{}", id),
+    }
+}
+
+fn spdx_package(
+    component: &Component,
+    id: &str,
+    path: Option<&str>,
+    digests: Option<&Digests>,
+) -> Value {
+    let mut package = json!({
+        "SPDXID": id,
+        "name": name(component, path, "NOASSERTION"),
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseDeclared": if is_spdx_expression(&component.license) {
+            component.license.as_str()
+        } else {
+            "NOASSERTION"
+        },
+    });
+
+    if !component.version.is_empty() {
+        package["versionInfo"] = json!(component.version);
+    }
+    if let Some(path) = path {
+        package["packageFileName"] = json!(path);
+    }
+    if let Some(digests) = digests {
+        package["checksums"] = spdx_checksums(digests);
+    }
+    if let Some(ref purl) = component.purl {
+        package["externalRefs"] = json!([{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl,
+        }]);
+    }
+
+    package
+}
+
+/// Both formats require a name, but e.g. a jar's manifest may not give one.
+fn name<'c>(component: &'c Component, path: Option<&'c str>, unknown: &'c str) -> &'c str {
+    match (component.name.as_str(), path) {
+        ("", Some(path)) => path,
+        ("", None) => unknown,
+        (name, _) => name,
+    }
+}
+
+fn spdx_checksums(digests: &Digests) -> Value {
+    json!([
+        { "algorithm": "SHA1", "checksumValue": digest::hex(&digests.sha1) },
+        { "algorithm": "SHA256", "checksumValue": digest::hex(&digests.sha256) },
+        { "algorithm": "MD5", "checksumValue": digest::hex(&digests.md5) },
+    ])
+}
+
+fn relationship(from: &str, kind: &str, to: &str) -> Value {
+    json!({
+        "spdxElementId": from,
+        "relationshipType": kind,
+        "relatedSpdxElement": to,
+    })
+}
+
+/// Each node's children, by id, with `None` for the top level.
+fn children(nodes: &[Node]) -> HashMap<Option<usize>, Vec<usize>> {
+    let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+    for (id, node) in nodes.iter().enumerate() {
+        children.entry(node.parent).or_default().push(id);
+    }
+    children
+}
+
+fn cyclonedx_children(
+    nodes: &[Node],
+    children: &HashMap<Option<usize>, Vec<usize>>,
+    parent: Option<usize>,
+) -> Vec<Value> {
+    children
+        .get(&parent)
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .map(|&id| {
+            let node = &nodes[id];
+            let bom_ref = format!("node-{}", id);
+            let mut component = match node.component {
+                Some(ref component) => cyclonedx_component(
+                    component,
+                    &bom_ref,
+                    Some(&node.path),
+                    node.digests.as_ref(),
+                ),
+                None => {
+                    let file = Component {
+                        name: node.path.clone(),
+                        ..Component::default()
+                    };
+                    let mut file =
+                        cyclonedx_component(&file, &bom_ref, None, node.digests.as_ref());
+                    file["type"] = json!("file");
+                    file
+                }
+            };
+
+            let nested = cyclonedx_children(nodes, children, Some(id));
+            if !nested.is_empty() {
+                component["components"] = json!(nested);
+            }
+            component
+        })
+        .collect()
+}
+
+fn cyclonedx_component(
+    component: &Component,
+    bom_ref: &str,
+    path: Option<&str>,
+    digests: Option<&Digests>,
+) -> Value {
+    let mut value = json!({
+        "type": "library",
+        "bom-ref": bom_ref,
+        "name": name(component, path, ""),
+    });
+
+    if !component.version.is_empty() {
+        value["version"] = json!(component.version);
+    }
+    if let Some(ref purl) = component.purl {
+        value["purl"] = json!(purl);
+    }
+    if !component.license.is_empty() {
+        value["licenses"] = if is_spdx_expression(&component.license) {
+            json!([{ "expression": component.license }])
+        } else {
+            json!([{ "license": { "name": component.license } }])
+        };
+    }
+    if let Some(digests) = digests {
+        value["hashes"] = json!([
+            { "alg": "MD5", "content": digest::hex(&digests.md5) },
+            { "alg": "SHA-1", "content": digest::hex(&digests.sha1) },
+            { "alg": "SHA-256", "content": digest::hex(&digests.sha256) },
+        ]);
+    }
+    if let Some(path) = path {
+        value["properties"] = json!([{ "name": "splayers:path", "value": path }]);
+    }
+
+    value
+}
+
+/// Roughly: identifiers separated by `AND`, `OR` or `WITH`, e.g. `MIT OR Apache-2.0`,
+/// as opposed to free text like `BSD License`.
+fn is_spdx_expression(license: &str) -> bool {
+    let tokens: Vec<&str> = license
+        .split(|c: char| c.is_whitespace() || '(' == c || ')' == c)
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    !tokens.is_empty()
+        && 1 == tokens.len() % 2
+        && tokens.iter().enumerate().all(|(i, token)| {
+            if 1 == i % 2 {
+                ["AND", "OR", "WITH"].contains(token)
+            } else {
+                token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.+:".contains(c))
+            }
+        })
+}
+
+/// For purl components and URLs: everything but unreserved characters.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn now() -> String {
+    simple_time::rfc3339_utc(simple_time::simple_time_sys(SystemTime::now()) / 1_000_000_000)
+}
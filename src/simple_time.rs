@@ -1,7 +1,5 @@
 use std::time;
 
-use crate::crates_time;
-
 pub fn simple_time(dur: time::Duration) -> u64 {
     dur.as_secs()
         .checked_mul(1_000_000_000)
@@ -14,19 +12,11 @@ pub fn simple_time_sys(val: time::SystemTime) -> u64 {
         .unwrap_or(0)
 }
 
-pub fn simple_time_tm(val: crates_time::Tm) -> u64 {
-    let timespec = val.to_timespec();
-    simple_time(time::Duration::new(
-        timespec.sec as u64,
-        timespec.nsec as u32,
-    ))
-}
-
 pub fn simple_time_epoch_seconds(seconds: u64) -> u64 {
     seconds.checked_mul(1_000_000_000).unwrap_or(0)
 }
 
-#[cfg(never)]
+#[cfg(any())]
 pub fn simple_time_ctime(val: &stat::Stat) -> u64 {
     if val.ctime <= 0 {
         0
@@ -34,3 +24,50 @@ pub fn simple_time_ctime(val: &stat::Stat) -> u64 {
         (val.ctime as u64).checked_mul(1_000_000_000).unwrap_or(0) + (val.ctime_nano as u64)
     }
 }
+
+/// e.g. `2019-03-01T12:34:56Z`, for seconds since the epoch.
+pub fn rfc3339_utc(seconds: u64) -> String {
+    let days = seconds / 86_400;
+    let time = seconds % 86_400;
+
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Seconds since the epoch, which may be negative, for a UTC date and time.
+pub fn utc_epoch_seconds(
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+) -> i64 {
+    // Howard Hinnant's days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    days * 86_400 + hour * 3600 + minute * 60 + second
+}
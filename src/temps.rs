@@ -9,6 +9,8 @@ use failure::Error;
 use failure::ResultExt;
 use tempfile::TempDir;
 
+const THREE_HEX_DIGITS: usize = 4096;
+
 #[derive(Debug)]
 pub struct Temps {
     dir: TempDir,
@@ -23,16 +25,21 @@ impl Temps {
         })
     }
 
-    pub fn insert<R: Read>(&mut self, mut from: R) -> Result<PathBuf, Error> {
-        let mut dest = self.dir.as_ref().to_path_buf();
-        let three_hex_digits = 4096;
-        let subdir = self.count / three_hex_digits;
-        let in_dir = self.count % three_hex_digits;
-        dest.push(format!("{}", subdir));
-        if 0 == in_dir {
-            fs::create_dir(&dest).expect("tempdir");
+    pub fn insert<R: Read>(&mut self, from: R) -> Result<PathBuf, Error> {
+        let (dest, result) = self.insert_partial(from)?;
+        result?;
+        Ok(dest)
+    }
+
+    /// Like `insert`, but a failing reader leaves behind whatever it produced, and its error.
+    pub fn insert_partial<R: Read>(
+        &mut self,
+        mut from: R,
+    ) -> Result<(PathBuf, io::Result<()>), Error> {
+        let dest = self.path(self.count);
+        if self.count.is_multiple_of(THREE_HEX_DIGITS) {
+            fs::create_dir(dest.parent().expect("in a subdir")).expect("tempdir");
         }
-        dest.push(format!("{:03x}.tmp", in_dir));
 
         self.count += 1;
 
@@ -44,14 +51,37 @@ impl Temps {
 
         loop {
             let mut buf = [0u8; 8 * 1024];
-            let found = from.read(&mut buf)?;
+            let found = match from.read(&mut buf) {
+                Ok(found) => found,
+                Err(e) => return Ok((dest, Err(e))),
+            };
             if 0 == found {
                 break;
             }
             tmp.write_all(&buf[..found]).expect("writing to temp file");
         }
 
-        Ok(dest)
+        Ok((dest, Ok(())))
+    }
+
+    /// Where we are, for `remove_since`.
+    pub fn mark(&self) -> usize {
+        self.count
+    }
+
+    /// Delete everything inserted since `mark`, e.g. by an attempt which is being abandoned.
+    pub fn remove_since(&mut self, mark: usize) {
+        for index in mark..self.count {
+            // not there if its insert failed
+            let _ = fs::remove_file(self.path(index));
+        }
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        let mut path = self.dir.as_ref().to_path_buf();
+        path.push(format!("{}", index / THREE_HEX_DIGITS));
+        path.push(format!("{:03x}.tmp", index % THREE_HEX_DIGITS));
+        path
     }
 
     pub fn into_dir(self) -> TempDir {
@@ -1,25 +1,59 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io;
+use std::io::BufRead;
 use std::io::Read;
+use std::io::Seek;
 use std::path::Path;
 use std::path::PathBuf;
 
 use failure::err_msg;
 use failure::Error;
 use failure::ResultExt;
-use walkdir;
 
+use crate::apk;
+use crate::details;
+use crate::details::Details;
+use crate::digest::Digests;
+use crate::dsc;
 use crate::file_type;
 use crate::file_type::FileType;
+use crate::file_type::TypeHint;
+use crate::git;
+use crate::git::GitView;
+use crate::iso9660;
+use crate::language;
+use crate::language::Classification;
+use crate::license;
+use crate::license::License;
+use crate::lzip::LzipDecoder;
+use crate::lzop::LzopDecoder;
+use crate::lzw::LzwDecoder;
 use crate::meta;
 use crate::mio;
 use crate::mio::Mio;
+use crate::options::Options;
+use crate::package;
+use crate::package::Package;
 use crate::temps::Temps;
 
 #[derive(Debug)]
 pub struct Entry {
     pub local: LocalEntry,
     pub children: Status,
+    /// Set if the children look like a package we understand, e.g. an apk's `.PKGINFO`.
+    pub package: Option<Package>,
+    /// The content's hashes, for regular files, if `Options::digests` was set.
+    pub digests: Option<Digests>,
+    /// Set if the content itself tells us something, e.g. a static library's symbol index.
+    pub details: Option<Details>,
+    /// A licence stated in a text file's content, if `Options::licenses` was set.
+    pub license: Option<License>,
+    /// The language and nature of the content, for anything we had the content of.
+    pub classification: Option<Classification>,
+    /// Set if the content was unpacked as the type its name suggested: only believed if that
+    /// used all of the content, and produced something.
+    pub type_hint: Option<TypeHint>,
 }
 
 #[derive(Debug)]
@@ -35,27 +69,95 @@ pub enum Status {
     Unrecognised,
     TooNested,
     Unsupported(FileType),
+    /// The item is encrypted, and we weren't given a password which opens it.
+    Encrypted,
     Error(String),
     Success(Vec<Entry>),
+    /// Salvaged from a damaged archive: what could be read, and the error which stopped us.
+    Partial(Vec<Entry>, String),
 }
 
-pub fn unpack_root<P: AsRef<Path>>(from: P, temps: &mut Temps) -> Result<Status, Error> {
-    if !from.as_ref().is_dir() {
-        return Ok(unpack_unknown(mio::Mio::from_path(from)?, temps, 0));
+/// Unpack a single file, as the entry for it, named for its file name.
+pub fn unpack_file<P: AsRef<Path>>(
+    from: P,
+    temps: &mut Temps,
+    options: &Options,
+) -> Result<Entry, Error> {
+    let from = from.as_ref();
+    let name = from
+        .file_name()
+        .unwrap_or(from.as_os_str())
+        .to_string_lossy()
+        .as_bytes()
+        .to_vec();
+
+    let temp = temps.insert(
+        fs::File::open(from).with_context(|_| format_err!("opening input path: {:?}", from))?,
+    )?;
+
+    let local = LocalEntry {
+        temp: Some(temp),
+        meta: meta::file(from)?,
+        path: name.into_boxed_slice(),
+    };
+
+    Ok(file_entry(local, from, temps, options))
+}
+
+/// A file from the filesystem, as an entry; a `.dsc`'s children are the source tree it lists.
+fn file_entry(local: LocalEntry, path: &Path, temps: &mut Temps, options: &Options) -> Entry {
+    if options.debian_source && is_dsc(path) {
+        local.into_entry_by(temps, options, |_, file_type, _, temps| {
+            (file_type, dsc::assemble(path, temps, options), None)
+        })
+    } else {
+        local.into_entry(temps, options, 0)
     }
+}
+
+/// Unpack the files in a directory, or a repository's, if it's a git repository.
+pub fn unpack_root<P: AsRef<Path>>(
+    from: P,
+    temps: &mut Temps,
+    options: &Options,
+) -> Result<Status, Error> {
+    if GitView::Files != options.git && git::is_repository(from.as_ref()) {
+        return Ok(unpack_repository(from.as_ref(), temps, options));
+    }
+
+    // files which will be presented as part of a source package, instead of on their own
+    let mut assembled = HashSet::new();
+    if options.debian_source {
+        for entry in walkdir::WalkDir::new(&from) {
+            let entry = entry?;
+            if entry.file_type().is_file() && is_dsc(entry.path()) {
+                assembled.extend(dsc::referenced(entry.path()));
+            }
+        }
+    }
+
+    // repositories are presented as their content, instead of walked into
+    let mut repositories = Vec::new();
+    let walker = walkdir::WalkDir::new(&from)
+        .into_iter()
+        .filter_entry(|entry| {
+            let repository = entry.depth() > 0
+                && entry.file_type().is_dir()
+                && GitView::Files != options.git
+                && git::is_repository(entry.path());
+            if repository {
+                repositories.push(entry.path().to_path_buf());
+            }
+            !repository
+        });
 
     let mut entries = Vec::new();
-    for entry in walkdir::WalkDir::new(&from) {
+    for entry in walker {
         let entry = entry?;
-        if entry.file_type().is_dir() {
+        if entry.file_type().is_dir() || assembled.contains(entry.path()) {
             continue;
         }
 
-        let relative_path = entry
-            .path()
-            .strip_prefix(&from)
-            .expect("dir walking confusion");
-
         let temp = if !entry.path().symlink_metadata()?.file_type().is_symlink() {
             Some(
                 temps
@@ -67,60 +169,205 @@ pub fn unpack_root<P: AsRef<Path>>(from: P, temps: &mut Temps) -> Result<Status,
             None
         };
 
-        entries.push(
-            LocalEntry {
-                temp,
-                meta: meta::file(entry.path())?,
-                path: relative_path
-                    .as_os_str()
-                    .to_str()
-                    .ok_or(err_msg(
-                        "unencodable path in local filesystem is unsupported",
-                    ))?
-                    .as_bytes()
-                    .to_vec()
-                    .into_boxed_slice(),
-            }
-            .into_entry(temps, 0),
-        )
+        let local = LocalEntry {
+            temp,
+            meta: meta::file(entry.path())?,
+            path: relative_path(&from, entry.path())?,
+        };
+
+        entries.push(file_entry(local, entry.path(), temps, options));
+    }
+
+    for repository in repositories {
+        let mut local = LocalEntry {
+            temp: None,
+            meta: meta::dir(&repository)?,
+            path: relative_path(&from, &repository)?,
+        }
+        .into_entry(temps, options, 0);
+
+        local.children = unpack_repository(&repository, temps, options);
+        local.package = package::identify(local.children.entries());
+        entries.push(local);
     }
 
     Ok(Status::Success(entries))
 }
 
-pub fn unpack_unknown(mut from: Mio, temps: &mut Temps, depth: u16) -> Status {
+fn relative_path<P: AsRef<Path>>(root: P, path: &Path) -> Result<Box<[u8]>, Error> {
+    Ok(path
+        .strip_prefix(root)
+        .expect("dir walking confusion")
+        .as_os_str()
+        .to_str()
+        .ok_or(err_msg(
+            "unencodable path in local filesystem is unsupported",
+        ))?
+        .as_bytes()
+        .to_vec()
+        .into_boxed_slice())
+}
+
+fn unpack_repository(git_dir: &Path, temps: &mut Temps, options: &Options) -> Status {
+    let mut kids = Vec::new();
+    let result = git::unpack_repository(git_dir, &options.git, temps, &mut kids);
+    into_status(result, kids, temps, options, 1)
+}
+
+fn is_dsc<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| "dsc" == ext)
+}
+
+pub fn unpack_unknown(mut from: Mio, temps: &mut Temps, options: &Options, depth: u16) -> Status {
+    let file_type = identify(&mut from, options);
+    unpack_identified(from, file_type, temps, options, depth)
+}
+
+fn identify(from: &mut Mio, options: &Options) -> FileType {
+    let header = from.header();
+    file_type::identify_further(
+        FileType::identify(&header),
+        &header,
+        || {
+            from.at(file_type::ISO9660_MAGIC_OFFSET, 5)
+                .unwrap_or_default()
+        },
+        || from.tail(file_type::ZIP_TRAILER_CAP).ok(),
+        options.zlib_streams,
+    )
+}
+
+/// If the content was inconclusive, try whatever the name suggests, falling back
+/// if that doesn't find anything.
+fn unpack_hinted(
+    from: Mio,
+    file_type: FileType,
+    name: &[u8],
+    temps: &mut Temps,
+    options: &Options,
+    depth: u16,
+) -> (FileType, Status, Option<TypeHint>) {
+    if let FileType::Binary | FileType::Source | FileType::Other = file_type {
+        // past the depth limit, `unpack_identified` says so
+        if let Some((hinted, extension)) = file_type::by_extension(name).filter(|_| depth < 128) {
+            // judged before anything it found is unpacked in turn, so a wrong guess costs
+            // one decode, and leaves nothing behind
+            let mark = temps.mark();
+            let mut kids = Vec::new();
+            match unpack_as(from.clone(), hinted, temps, options, &mut kids) {
+                Some(Ok(())) if produced_something(&kids) => {
+                    let status = into_status(Ok(()), kids, temps, options, depth);
+                    let hint = TypeHint {
+                        file_type: hinted,
+                        extension,
+                    };
+                    return (hinted, status, Some(hint));
+                }
+                _ => temps.remove_since(mark),
+            }
+        }
+    }
+
+    let status = unpack_identified(from, file_type, temps, options, depth);
+    (file_type, status, None)
+}
+
+/// Whether a hinted unpack is worth believing: it worked, and not just to nothing.
+fn produced_something(kids: &[LocalEntry]) -> bool {
+    kids.iter().any(|kid| match kid.temp {
+        Some(ref temp) => fs::metadata(temp)
+            .map(|meta| 0 != meta.len())
+            .unwrap_or(false),
+        // directories and the like, but not empty files
+        None => meta::ItemType::RegularFile != kid.meta.item_type,
+    })
+}
+
+fn unpack_identified(
+    from: Mio,
+    file_type: FileType,
+    temps: &mut Temps,
+    options: &Options,
+    depth: u16,
+) -> Status {
     if depth >= 128 {
         return Status::TooNested;
     }
 
-    match match FileType::identify(&from.header()) {
-        FileType::Deb => unpack_deb(from, temps),
-        FileType::Tar => unpack_tar(from, temps),
-        FileType::Zip => unpack_zip(from, temps),
-        FileType::Bz => unpack_bz(from, temps),
-        FileType::Gz => unpack_gz(from, temps),
-        FileType::Xz => unpack_xz(from, temps),
-        FileType::Empty => return Status::Unnecessary,
-        FileType::Other => return Status::Unrecognised,
-        other => return Status::Unsupported(other),
-    } {
-        Ok(kids) => Status::Success(
-            kids.into_iter()
-                .map(|local| local.into_entry(temps, depth))
-                .collect(),
-        ),
-        Err(e) => Status::Error(format!("{}", e)),
+    let mut kids = Vec::new();
+    match unpack_as(from, file_type, temps, options, &mut kids) {
+        Some(result) => into_status(result, kids, temps, options, depth),
+        None => match file_type {
+            FileType::Empty => Status::Unnecessary,
+            FileType::Other => Status::Unrecognised,
+            other => Status::Unsupported(other),
+        },
     }
 }
 
-fn unpack_deb(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
-    use ar;
+/// Find the kids, if we can unpack this type at all.
+fn unpack_as(
+    from: Mio,
+    file_type: FileType,
+    temps: &mut Temps,
+    options: &Options,
+    kids: &mut Vec<LocalEntry>,
+) -> Option<Result<(), Error>> {
+    Some(match file_type {
+        FileType::Deb | FileType::Ar => unpack_ar(from, temps, kids),
+        FileType::Tar => unpack_tar(from, temps, kids),
+        FileType::Zip(_) => unpack_zip(from, temps, options, kids),
+        FileType::Bz => unpack_bz(from, temps, options, kids),
+        FileType::Gz => unpack_gz(from, temps, options, kids),
+        FileType::Xz => unpack_xz(from, temps, options, kids),
+        FileType::Deflate => unpack_deflate(from, temps, options, kids),
+        FileType::Lzma => unpack_lzma(from, temps, options, kids),
+        FileType::Lzip => unpack_lzip(from, temps, options, kids),
+        FileType::Compress => unpack_compress(from, temps, options, kids),
+        FileType::Lzop => unpack_lzop(from, temps, options, kids),
+        FileType::Lz4 => unpack_lz4(from, temps, options, kids),
+        FileType::Brotli => unpack_brotli(from, temps, options, kids),
+        FileType::Zlib => unpack_zlib(from, temps, kids),
+        FileType::GitPack => git::unpack_pack(from, temps, kids),
+        FileType::Iso9660 => iso9660::unpack(from, temps, kids),
+        _ => return None,
+    })
+}
 
-    let mut entries = Vec::new();
+/// Turn what an unpacker found into entries, which unpacks them in turn.
+fn into_status(
+    result: Result<(), Error>,
+    kids: Vec<LocalEntry>,
+    temps: &mut Temps,
+    options: &Options,
+    depth: u16,
+) -> Status {
+    match result {
+        Err(ref e) if !options.salvage || kids.is_empty() => Status::Error(format!("{}", e)),
+        result => {
+            let kids = kids
+                .into_iter()
+                .map(|local| local.into_entry(temps, options, depth))
+                .collect();
+            match result {
+                Ok(()) => Status::Success(kids),
+                Err(e) => Status::Partial(kids, format!("{}", e)),
+            }
+        }
+    }
+}
+
+fn unpack_ar(from: Mio, temps: &mut Temps, entries: &mut Vec<LocalEntry>) -> Result<(), Error> {
+    use ar;
 
     let mut decoder = ar::Archive::new(from);
     while let Some(entry) = decoder.next_entry() {
         let entry = entry?;
+        // BSD symbol tables, which `ar` only spots if it's seen a BSD-style long name
+        if entry.header().identifier().starts_with(b"__.SYMDEF") {
+            continue;
+        }
+
         let size = entry.header().size();
         let path = entry.header().identifier().to_vec().into_boxed_slice();
         let meta = meta::for_ar(entry.header())?;
@@ -132,59 +379,225 @@ fn unpack_deb(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
         });
     }
 
-    Ok(entries)
+    Ok(())
 }
 
-fn unpack_tar<R: Read>(from: R, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
+fn unpack_tar<R: Read>(
+    from: R,
+    temps: &mut Temps,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
     use tar;
 
-    let mut entries = Vec::new();
-
     for tar in tar::Archive::new(from).entries()? {
         let tar = tar?;
         let size = tar.header().size()?;
         let path = tar.path_bytes().to_vec().into_boxed_slice();
         let meta = meta::for_tar(tar.header(), tar.link_name_bytes())?;
+        let hard_link = match tar.header().entry_type() {
+            tar::EntryType::Link => tar.link_name_bytes().map(|target| target.into_owned()),
+            _ => None,
+        };
 
-        entries.push(LocalEntry {
-            meta,
-            path,
-            temp: insert_if_non_empty(temps, tar, size)?,
-        });
+        let temp = match hard_link {
+            Some(target) => hard_link_content(temps, entries, &target)?,
+            None => insert_if_non_empty(temps, tar, size)?,
+        };
+
+        entries.push(LocalEntry { meta, path, temp });
     }
 
-    Ok(entries)
+    Ok(())
 }
 
-fn unpack_zip(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
-    use zip;
+/// A copy of the content of the earlier entry a hard link names, if it had any.
+fn hard_link_content(
+    temps: &mut Temps,
+    entries: &[LocalEntry],
+    target: &[u8],
+) -> Result<Option<PathBuf>, Error> {
+    let relative = |path: &[u8]| path.strip_prefix(b"./").unwrap_or(path).to_vec();
+    let target = relative(target);
+    let linked = entries
+        .iter()
+        .rev()
+        .find(|entry| relative(&entry.path) == target)
+        .and_then(|entry| entry.temp.as_ref());
 
-    let mut entries = Vec::new();
+    Ok(match linked {
+        Some(temp) => Some(temps.insert(fs::File::open(temp)?)?),
+        None => None,
+    })
+}
+
+fn unpack_zip(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    use zip::result::ZipError;
 
-    let mut archive = zip::read::ZipArchive::new(from)?;
+    let mut archive = match zip::read::ZipArchive::new(from.clone()) {
+        Ok(archive) => archive,
+        Err(e) => {
+            if options.salvage {
+                // the broken central directory is more interesting than where the scan stopped
+                let _ = salvage_zip(from, temps, entries);
+            }
+            return Err(e.into());
+        }
+    };
     for i in 0..archive.len() {
-        let entry = archive.by_index(i)?;
+        let (size, path, mut meta) = {
+            let entry = archive.by_index_raw(i)?;
+            (
+                entry.size(),
+                entry.name_raw().to_vec().into_boxed_slice(),
+                meta::for_zip(&entry)?,
+            )
+        };
 
-        let size = entry.size();
-        let path = entry.name_raw().to_vec().into_boxed_slice();
-        let meta = meta::for_zip(&entry)?;
+        let plain = match archive.by_index(i) {
+            Ok(entry) => Some(insert_if_non_empty(temps, entry, size)?),
+            Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let temp = match plain {
+            Some(temp) => temp,
+            None => {
+                // reported whether or not we manage to decrypt it
+                meta.encryption = Some(match meta.encryption {
+                    Some(meta::Encryption::Aes) => meta::Encryption::Aes,
+                    _ => meta::Encryption::ZipCrypto,
+                });
+                decrypt_zip(&mut archive, i, &path, temps, options)
+            }
+        };
+
+        entries.push(LocalEntry { meta, path, temp });
+    }
+
+    Ok(())
+}
+
+/// Walk the local file headers from the start of the file, ignoring the central directory.
+/// A streaming writer puts a member's sizes in a data descriptor, after its data, so the end
+/// of a deflated member is found by inflating it; a stored member's can't be found at all.
+fn salvage_zip(from: Mio, temps: &mut Temps, entries: &mut Vec<LocalEntry>) -> Result<(), Error> {
+    let mut from = io::BufReader::new(from);
+
+    loop {
+        let mut header = [0u8; 30];
+        from.read_exact(&mut header[..4])?;
+        if b"PK\x03\x04" != &header[..4] {
+            // the central directory, or whatever's left
+            return Ok(());
+        }
+        from.read_exact(&mut header[4..])?;
+
+        let flags = u16::from_le_bytes([header[6], header[7]]);
+        if 0 == flags & 0x08 {
+            let mut member = io::Cursor::new(header).chain(&mut from);
+            let entry = zip::read::read_zipfile_from_stream(&mut member)?
+                .ok_or_else(|| err_msg("local header vanished"))?;
+            let size = entry.size();
+            let path = entry.name_raw().to_vec().into_boxed_slice();
+            let meta = meta::for_zip(&entry)?;
+
+            entries.push(LocalEntry {
+                meta,
+                path,
+                temp: insert_if_non_empty(temps, entry, size)?,
+            });
+            continue;
+        }
+
+        let method = u16::from_le_bytes([header[8], header[9]]);
+        if 8 != method {
+            return Err(format_err!(
+                "can't find the end of a member with a data descriptor, compressed with {}",
+                method
+            ));
+        }
+
+        // the zip crate won't read a header without sizes, so give it one with nothing to read
+        let mut bare = header;
+        bare[6] &= !0x08;
+        bare[8..10].copy_from_slice(&[0, 0]);
+        bare[14..26].copy_from_slice(&[0; 12]);
+        let (path, meta, zip64) = {
+            let mut member = io::Cursor::new(bare).chain(&mut from);
+            let entry = zip::read::read_zipfile_from_stream(&mut member)?
+                .ok_or_else(|| err_msg("local header vanished"))?;
+            let zip64 = meta::zip_extra_fields(entry.extra_data()).any(|(id, _)| 0x0001 == id);
+            (
+                entry.name_raw().to_vec().into_boxed_slice(),
+                meta::for_zip(&entry)?,
+                zip64,
+            )
+        };
+
+        let mut content = flate2::CrcReader::new(flate2::bufread::DeflateDecoder::new(&mut from));
+        let temp = temps.insert(&mut content)?;
+        let (crc, size) = (content.crc().sum(), fs::metadata(&temp)?.len());
+
+        // the descriptor: an optional signature, the crc, then the sizes
+        let mut descriptor = [0u8; 4];
+        from.read_exact(&mut descriptor)?;
+        if b"PK\x07\x08" == &descriptor {
+            from.read_exact(&mut descriptor)?;
+        }
+        if crc != u32::from_le_bytes(descriptor) {
+            return Err(format_err!(
+                "crc mismatch for {:?}",
+                String::from_utf8_lossy(&path)
+            ));
+        }
+        let mut sizes = [0u8; 16];
+        from.read_exact(&mut sizes[..if zip64 { 16 } else { 8 }])?;
 
         entries.push(LocalEntry {
             meta,
             path,
-            temp: insert_if_non_empty(temps, entry, size)?,
+            temp: if 0 == size {
+                fs::remove_file(&temp)?;
+                None
+            } else {
+                Some(temp)
+            },
         });
     }
+}
 
-    Ok(entries)
+/// `None` if we have no password, the password is wrong, or the data fails its checksum.
+/// A wrong ZipCrypto password gets past its one byte check one time in 256, so only the
+/// checksum, which is checked once the entry has been read to its end, is conclusive.
+fn decrypt_zip(
+    archive: &mut zip::read::ZipArchive<Mio>,
+    index: usize,
+    path: &[u8],
+    temps: &mut Temps,
+    options: &Options,
+) -> Option<PathBuf> {
+    let password = options.password.as_ref()?(path)?;
+    let entry = archive.by_index_decrypt(index, &password).ok()?.ok()?;
+    temps.insert(entry).ok()
 }
 
 enum EmbeddedTar<T> {
-    Found(Vec<LocalEntry>),
+    Found,
     Absent(io::BufReader<T>),
 }
 
-fn embedded_tar<F, T: Read>(from: Mio, make: F, temps: &mut Temps) -> Result<EmbeddedTar<T>, Error>
+fn embedded_tar<F, T: Read>(
+    from: Mio,
+    make: F,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<EmbeddedTar<T>, Error>
 where
     F: Fn(Mio) -> T,
 {
@@ -194,112 +607,551 @@ where
         return Ok(EmbeddedTar::Absent(decoder));
     }
 
-    Ok(match unpack_tar(decoder, temps) {
-        Ok(v) => EmbeddedTar::Found(v),
-        Err(_) => EmbeddedTar::Absent(io::BufReader::new(make(backup))),
+    Ok(match unpack_tar(decoder, temps, entries) {
+        Ok(()) => EmbeddedTar::Found,
+        Err(e) if options.salvage && !entries.is_empty() => return Err(e),
+        Err(_) => {
+            entries.clear();
+            EmbeddedTar::Absent(io::BufReader::new(make(backup)))
+        }
     })
 }
 
-fn unpack_bz(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
+/// A decoder for a stream with nothing to mark its end, failing if the stream ends before its
+/// input does: that much of a file decoding is more likely chance than the real thing.
+struct WholeInput<D> {
+    decoder: D,
+    at_end: fn(&mut D) -> io::Result<bool>,
+}
+
+impl<D: Read> Read for WholeInput<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.decoder.read(buf)?;
+        if 0 == len && !buf.is_empty() && !(self.at_end)(&mut self.decoder)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "data after the end of the stream",
+            ));
+        }
+        Ok(len)
+    }
+}
+
+fn unpack_bz(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
     use bzip2;
 
-    let decoder = match embedded_tar(from, bzip2::read::BzDecoder::new, temps)? {
-        EmbeddedTar::Found(vec) => return Ok(vec),
+    let decoder = match embedded_tar(
+        from,
+        bzip2::read::MultiBzDecoder::new,
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
         EmbeddedTar::Absent(decoder) => decoder,
     };
 
     let temp = Some(temps.insert(decoder)?);
 
-    Ok(vec![LocalEntry {
+    entries.push(LocalEntry {
         temp,
         meta: meta::just_stream(),
         path: b"..bz2".to_vec().into_boxed_slice(),
-    }])
+    });
+
+    Ok(())
 }
 
-fn unpack_gz(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
-    use flate2;
+fn unpack_gz(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let (members, failure) = gz_members(from, temps)?;
+    let failure = match failure {
+        Some(e) if !options.salvage => return Err(e.into()),
+        other => other,
+    };
+
+    // e.g. Alpine's apks: each member is a tar of its own, so present them separately
+    if members.len() > 1 && members.iter().all(|member| is_tar_file(&member.temp)) {
+        let apk_names = apk::segment_names(
+            &members
+                .iter()
+                .map(|member| &member.temp)
+                .collect::<Vec<_>>(),
+        );
+
+        for (i, member) in members.into_iter().enumerate() {
+            entries.push(LocalEntry {
+                meta: meta::gz(&member.header)?,
+                path: match apk_names {
+                    Some(ref names) => names[i].as_bytes().to_vec(),
+                    None => member
+                        .header
+                        .filename()
+                        .map(|name| name.to_vec())
+                        .unwrap_or_else(|| format!("..gz.{}", i).into_bytes()),
+                }
+                .into_boxed_slice(),
+                temp: Some(member.temp),
+            });
+        }
+    } else {
+        let header = members[0].header.clone();
+        let temp = if 1 == members.len() {
+            members[0].temp.clone()
+        } else {
+            concatenate(temps, members.iter().map(|member| &member.temp))?
+        };
 
-    let decoder = match embedded_tar(from, flate2::read::GzDecoder::new, temps)? {
-        EmbeddedTar::Found(vec) => return Ok(vec),
+        match embedded_tar(Mio::from_path(&temp)?, |mio| mio, temps, options, entries)? {
+            EmbeddedTar::Found => fs::remove_file(&temp)?,
+            EmbeddedTar::Absent(_) => entries.push(LocalEntry {
+                temp: Some(temp),
+                meta: meta::gz(&header)?,
+                path: header
+                    .filename()
+                    .unwrap_or(b"..gz")
+                    .to_vec()
+                    .into_boxed_slice(),
+            }),
+        }
+    }
+
+    match failure {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+struct GzMember {
+    header: flate2::GzHeader,
+    temp: PathBuf,
+}
+
+/// Decompress each member of a (possibly concatenated) gzip file into its own temp.
+/// Trailing junk after a member is ignored. A decoding error stops the process, but
+/// the damaged member is still returned, along with the error.
+fn gz_members(from: Mio, temps: &mut Temps) -> Result<(Vec<GzMember>, Option<io::Error>), Error> {
+    let mut members = Vec::new();
+    let mut input = io::BufReader::new(from);
+
+    loop {
+        let mut decoder = flate2::bufread::GzDecoder::new(input);
+        let (temp, result) = temps.insert_partial(&mut decoder)?;
+        let header = match decoder.header() {
+            Some(header) => header.clone(),
+            None => return Err(err_msg("invalid header")),
+        };
+
+        members.push(GzMember { header, temp });
+
+        if let Err(e) = result {
+            return Ok((members, Some(e)));
+        }
+
+        input = decoder.into_inner();
+        if !next_is_gz(&mut input)? {
+            return Ok((members, None));
+        }
+    }
+}
+
+/// Whether another gzip member follows. The magic may straddle the end of the buffer,
+/// which `fill_buf` won't refill while there's anything left in it.
+fn next_is_gz<R: BufRead + Seek>(input: &mut R) -> io::Result<bool> {
+    if 1 == input.fill_buf()?.len() {
+        // seeking discards the buffer, so the next fill starts here
+        let pos = input.stream_position()?;
+        input.seek(io::SeekFrom::Start(pos))?;
+    }
+
+    Ok(input.fill_buf()?.starts_with(&[0x1f, 0x8b]))
+}
+
+fn is_tar_file<P: AsRef<Path>>(path: P) -> bool {
+    file_type::is_probably_tar(&Mio::from_path(path).expect("working with temps").header())
+}
+
+/// Join some temps into a new one, removing the originals.
+fn concatenate<'p, I>(temps: &mut Temps, parts: I) -> Result<PathBuf, Error>
+where
+    I: Iterator<Item = &'p PathBuf> + Clone,
+{
+    let mut joined: Box<dyn Read> = Box::new(io::empty());
+    for part in parts.clone() {
+        joined = Box::new(joined.chain(fs::File::open(part)?));
+    }
+
+    let temp = temps.insert(joined)?;
+
+    for part in parts {
+        fs::remove_file(part)?;
+    }
+
+    Ok(temp)
+}
+
+fn unpack_xz(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    use xz2;
+
+    let decoder = match embedded_tar(
+        from,
+        xz2::read::XzDecoder::new_multi_decoder,
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
         EmbeddedTar::Absent(decoder) => decoder,
     };
 
-    let header = decoder
-        .get_ref()
-        .header()
-        .ok_or(err_msg("invalid header"))?
-        .clone();
     let temp = Some(temps.insert(decoder)?);
 
-    Ok(vec![LocalEntry {
+    entries.push(LocalEntry {
         temp,
-        meta: meta::gz(&header)?,
-        path: header
-            .filename()
-            .unwrap_or(b"..gz")
-            .to_vec()
-            .into_boxed_slice(),
-    }])
+        meta: meta::just_stream(),
+        path: b"..xz".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+fn unpack_deflate(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(
+        from,
+        |mio| WholeInput {
+            decoder: flate2::bufread::DeflateDecoder::new(io::BufReader::new(mio)),
+            at_end: |decoder| Ok(decoder.get_mut().fill_buf()?.is_empty()),
+        },
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..deflate".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
 }
 
-fn unpack_xz(from: Mio, temps: &mut Temps) -> Result<Vec<LocalEntry>, Error> {
+fn unpack_lzma(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
     use xz2;
 
-    let decoder = match embedded_tar(from, xz2::read::XzDecoder::new, temps)? {
-        EmbeddedTar::Found(vec) => return Ok(vec),
+    let decoder = match embedded_tar(
+        from,
+        |mio| {
+            let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX).expect("lzma decoder");
+            WholeInput {
+                decoder: xz2::bufread::XzDecoder::new_stream(io::BufReader::new(mio), stream),
+                at_end: |decoder| Ok(decoder.get_mut().fill_buf()?.is_empty()),
+            }
+        },
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
         EmbeddedTar::Absent(decoder) => decoder,
     };
 
     let temp = Some(temps.insert(decoder)?);
 
-    Ok(vec![LocalEntry {
+    entries.push(LocalEntry {
         temp,
         meta: meta::just_stream(),
-        path: b"..xz".to_vec().into_boxed_slice(),
-    }])
+        path: b"..lzma".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+fn unpack_lzip(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(
+        from,
+        |mio| LzipDecoder::new(io::BufReader::new(mio)),
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..lz".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
 }
 
+fn unpack_compress(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(
+        from,
+        |mio| LzwDecoder::new(io::BufReader::new(mio)),
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..Z".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+fn unpack_lzop(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(from, LzopDecoder::new, temps, options, entries)? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..lzo".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+fn unpack_lz4(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(
+        from,
+        lz4_flex::frame::FrameDecoder::new,
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..lz4".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+fn unpack_brotli(
+    from: Mio,
+    temps: &mut Temps,
+    options: &Options,
+    entries: &mut Vec<LocalEntry>,
+) -> Result<(), Error> {
+    let decoder = match embedded_tar(
+        from,
+        |mio| WholeInput {
+            decoder: brotli_decompressor::Decompressor::new(mio, 4096),
+            // reading past the end fails if the decoder has input left over in its own buffer
+            at_end: |decoder| {
+                Ok(0 == decoder.read(&mut [0])? && 0 == decoder.get_mut().read(&mut [0])?)
+            },
+        },
+        temps,
+        options,
+        entries,
+    )? {
+        EmbeddedTar::Found => return Ok(()),
+        EmbeddedTar::Absent(decoder) => decoder,
+    };
+
+    let temp = Some(temps.insert(decoder)?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..br".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+/// Just the stream: nothing much is wrapped in zlib without a further header.
+fn unpack_zlib(from: Mio, temps: &mut Temps, entries: &mut Vec<LocalEntry>) -> Result<(), Error> {
+    let temp = Some(temps.insert(flate2::read::ZlibDecoder::new(from))?);
+
+    entries.push(LocalEntry {
+        temp,
+        meta: meta::just_stream(),
+        path: b"..zlib".to_vec().into_boxed_slice(),
+    });
+
+    Ok(())
+}
+
+/// The content of an entry which claims to be `size` long; failing if it's any shorter, as
+/// it is if the archive's been cut off.
 fn insert_if_non_empty<R: Read>(
     temps: &mut Temps,
     from: R,
     size: u64,
 ) -> Result<Option<PathBuf>, Error> {
-    Ok(if 0 == size {
-        None
-    } else {
-        Some(temps.insert(from.take(size))?)
-    })
+    if 0 == size {
+        return Ok(None);
+    }
+
+    let temp = temps.insert(from.take(size))?;
+    let found = fs::metadata(&temp)?.len();
+    if found != size {
+        return Err(format_err!(
+            "truncated: expected {} bytes, found {}",
+            size,
+            found
+        ));
+    }
+
+    Ok(Some(temp))
 }
 
 impl LocalEntry {
-    fn into_entry(mut self, temps: &mut Temps, depth: u16) -> Entry {
-        let children = if let Some(temp) = self.temp.as_ref() {
-            unpack_unknown(
-                Mio::from_path(temp).expect("working with temps"),
-                temps,
-                depth + 1,
-            )
+    fn into_entry(self, temps: &mut Temps, options: &Options, depth: u16) -> Entry {
+        self.into_entry_by(temps, options, |from, file_type, path, temps| {
+            unpack_hinted(from, file_type, path, temps, options, depth + 1)
+        })
+    }
+
+    /// As `into_entry`, but `unpack` makes the children from the content and its type.
+    fn into_entry_by<F>(mut self, temps: &mut Temps, options: &Options, unpack: F) -> Entry
+    where
+        F: FnOnce(Mio, FileType, &[u8], &mut Temps) -> (FileType, Status, Option<TypeHint>),
+    {
+        let (children, details, license, classification, type_hint) =
+            if let Some(temp) = self.temp.as_ref() {
+                let mut from = Mio::from_path(temp).expect("working with temps");
+                let file_type = identify(&mut from, options);
+                let header = from.header();
+                let (file_type, children, type_hint) = unpack(from, file_type, &self.path, temps);
+                let classification = language::classify(&self.path, file_type, &header);
+                let license = match file_type {
+                    FileType::Source | FileType::Other if options.licenses => {
+                        license::of(&self.path, temp)
+                    }
+                    _ => None,
+                };
+                (
+                    children,
+                    details::of(file_type, temp),
+                    license,
+                    Some(classification),
+                    type_hint,
+                )
+            } else if self.meta.encryption.is_some() {
+                (Status::Encrypted, None, None, None, None)
+            } else {
+                (Status::Unnecessary, None, None, None, None)
+            };
+
+        let digests = if !options.digests {
+            None
+        } else if let Some(temp) = self.temp.as_ref() {
+            Digests::of_path(temp).ok()
+        } else if meta::ItemType::RegularFile == self.meta.item_type
+            && self.meta.encryption.is_none()
+        {
+            Some(Digests::empty())
         } else {
-            Status::Unnecessary
+            None
         };
 
         if children.fully_consumed() {
             self.temp = None;
         }
 
+        let package = package::identify(children.entries());
+
         Entry {
             children,
             local: self,
+            package,
+            digests,
+            details,
+            license,
+            classification,
+            type_hint,
         }
     }
 }
 
 impl Status {
-    fn fully_consumed(&self) -> bool {
+    /// The children, if we managed to unpack any.
+    pub fn entries(&self) -> &[Entry] {
         match *self {
-            Status::Success(ref v) if !v.is_empty() => true,
-            _ => false,
+            Status::Success(ref v) | Status::Partial(ref v, _) => v,
+            _ => &[],
         }
     }
+
+    fn fully_consumed(&self) -> bool {
+        matches!(*self, Status::Success(ref v) if !v.is_empty())
+    }
 }
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io::Write;
+
+use splayers::Entry;
+use splayers::Package;
+use splayers::Status;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn gz(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+const PKGINFO: &[u8] = b"# Generated by abuild 3.10.0
+pkgname = hello
+pkgver = 2.12-r0
+pkgdesc = The GNU Hello program
+url = https://www.gnu.org/software/hello/
+builddate = 1672531200
+size = 53248
+arch = x86_64
+origin = hello
+maintainer = Someone <someone@example.org>
+license = GPL-3.0-or-later
+depend = so:libc.musl-x86_64.so.1
+depend = /bin/sh
+provides = cmd:hello=2.12-r0
+datahash = 0123456789abcdef
+";
+
+/// Each segment is a gzip member of its own, concatenated.
+fn apk(signed: bool) -> Vec<u8> {
+    let mut apk = Vec::new();
+    if signed {
+        apk.extend(gz(&tar(&[(".SIGN.RSA.someone.rsa.pub", b"not really")])));
+    }
+    apk.extend(gz(&tar(&[(".PKGINFO", PKGINFO)])));
+    apk.extend(gz(&tar(&[("usr/bin/hello", b"#!/bin/sh\necho hello\n")])));
+    apk
+}
+
+/// Unpack a directory holding just `name`, so its entry, and package, can be seen.
+fn unpack_in_dir(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("in");
+    fs::create_dir(&input).unwrap();
+    fs::write(input.join(name), data).unwrap();
+    let unpack = Unpack::unpack_into(&input, dir.path()).unwrap();
+    (dir, unpack)
+}
+
+fn only(status: &Status) -> &Entry {
+    match status {
+        Status::Success(entries) if 1 == entries.len() => &entries[0],
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+fn names(entry: &Entry) -> Vec<String> {
+    entry
+        .children
+        .entries()
+        .iter()
+        .map(|entry| String::from_utf8_lossy(&entry.local.path).to_string())
+        .collect()
+}
+
+#[test]
+fn signed_and_unsigned() {
+    for &(signed, expected) in &[
+        (true, &["signature", "control", "data"][..]),
+        (false, &["control", "data"][..]),
+    ] {
+        let (_dir, unpack) = unpack_in_dir("hello-2.12-r0.apk", &apk(signed));
+        let apk = only(unpack.status());
+        assert_eq!(expected, &names(apk)[..]);
+
+        let info = match apk.package {
+            Some(Package::Apk(ref info)) => info,
+            ref other => panic!("unexpected package: {:?}", other),
+        };
+        assert_eq!("hello", info.name);
+        assert_eq!("2.12-r0", info.version);
+        assert_eq!("The GNU Hello program", info.description);
+        assert_eq!("GPL-3.0-or-later", info.license);
+        assert_eq!("x86_64", info.arch);
+        assert_eq!(Some(1_672_531_200), info.build_date);
+        assert_eq!(Some(53248), info.size);
+        assert_eq!(vec!["so:libc.musl-x86_64.so.1", "/bin/sh"], info.depends);
+        assert_eq!(vec!["cmd:hello=2.12-r0"], info.provides);
+        // everything, but the comment
+        assert_eq!(14, info.fields.len());
+        assert_eq!(
+            ("datahash".to_string(), "0123456789abcdef".to_string()),
+            info.fields[13]
+        );
+    }
+}
+
+#[test]
+fn concatenated_tars_which_arent_an_apk() {
+    let mut data = gz(&tar(&[("README", b"first\n")]));
+    data.extend(gz(&tar(&[("NEWS", b"second\n")])));
+
+    let (_dir, unpack) = unpack_in_dir("two.tar.gz", &data);
+    let two = only(unpack.status());
+    assert!(two.package.is_none());
+    assert_eq!(vec!["..gz.0", "..gz.1"], names(two));
+}
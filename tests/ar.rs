@@ -0,0 +1,113 @@
+use std::fs;
+
+use splayers::ArFlavour;
+use splayers::Details;
+use splayers::Options;
+use splayers::Unpack;
+
+const LONG: &str = "a_rather_long_object_name.o";
+
+/// A member header: the name field is used as given, so can hold `/0` or `#1/28`.
+fn member(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut member = format!(
+        "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+        name,
+        0,
+        0,
+        0,
+        "100644",
+        data.len()
+    )
+    .into_bytes();
+    member.extend_from_slice(data);
+    if 1 == data.len() % 2 {
+        member.push(b'\n');
+    }
+    member
+}
+
+fn archive(members: &[Vec<u8>]) -> Vec<u8> {
+    let mut archive = b"!<arch>\n".to_vec();
+    for member in members {
+        archive.extend_from_slice(member);
+    }
+    archive
+}
+
+fn unpack(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+fn check(unpack: &Unpack, flavour: ArFlavour) {
+    let root = unpack.root().unwrap();
+
+    let names: Vec<_> = root
+        .children
+        .entries()
+        .iter()
+        .map(|entry| String::from_utf8_lossy(&entry.local.path).to_string())
+        .collect();
+    assert_eq!(vec![LONG, "short.o"], names);
+
+    match root.details {
+        Some(Details::Ar(ref index)) => {
+            assert_eq!(flavour, index.flavour);
+            let symbols: Vec<&[u8]> = index.symbols.iter().map(|s| &s[..]).collect();
+            assert_eq!(vec![&b"hello"[..], b"goodbye"], symbols);
+        }
+        ref other => panic!("no ar index: {:?}", other),
+    }
+}
+
+#[test]
+fn gnu_long_names_and_symbols() {
+    let mut symbols = Vec::new();
+    symbols.extend_from_slice(&2u32.to_be_bytes());
+    symbols.extend_from_slice(&0u32.to_be_bytes());
+    symbols.extend_from_slice(&0u32.to_be_bytes());
+    symbols.extend_from_slice(b"hello\0goodbye\0");
+
+    let data = archive(&[
+        member("/", &symbols),
+        member("//", format!("{}/\n", LONG).as_bytes()),
+        member("/0", b"long\n"),
+        member("short.o/", b"short\n"),
+    ]);
+
+    let (_dir, unpack) = unpack("libhello.a", &data);
+    check(&unpack, ArFlavour::Gnu);
+}
+
+#[test]
+fn bsd_long_names_and_symdef() {
+    let strings = b"hello\0goodbye\0";
+    let mut symdef = Vec::new();
+    symdef.extend_from_slice(&16u32.to_le_bytes());
+    for (name, offset) in &[(0u32, 0u32), (6, 0)] {
+        symdef.extend_from_slice(&name.to_le_bytes());
+        symdef.extend_from_slice(&offset.to_le_bytes());
+    }
+    symdef.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+    symdef.extend_from_slice(strings);
+
+    let inline = |name: &[u8], data: &[u8]| {
+        let mut padded = name.to_vec();
+        padded.resize((name.len() + 4) / 4 * 4, 0);
+        let label = format!("#1/{}", padded.len());
+        padded.extend_from_slice(data);
+        member(&label, &padded)
+    };
+
+    let data = archive(&[
+        inline(b"__.SYMDEF SORTED", &symdef),
+        inline(LONG.as_bytes(), b"long\n"),
+        member("short.o", b"short\n"),
+    ]);
+
+    let (_dir, unpack) = unpack("libhello.a", &data);
+    check(&unpack, ArFlavour::Bsd);
+}
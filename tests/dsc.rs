@@ -0,0 +1,140 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use md5::Digest;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn md5_hex(data: &[u8]) -> String {
+    md5::Md5::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Write a source package into `dir`, listing each file's size with `size`.
+fn source_package(dir: &Path, size: impl Fn(usize) -> usize) {
+    let files = [
+        (
+            "hello_1.0.orig.tar.gz",
+            tar_gz(&[
+                ("hello-1.0/README", b"hello, world\n"),
+                ("hello-1.0/debian/control", b"Source: upstream's idea\n"),
+            ]),
+        ),
+        (
+            "hello_1.0-1.debian.tar.gz",
+            tar_gz(&[
+                ("debian/control", b"Source: hello\n"),
+                ("debian/rules", b"#!/usr/bin/make -f\n"),
+            ]),
+        ),
+    ];
+
+    let mut dsc = b"Format: 3.0 (quilt)\nSource: hello\nVersion: 1.0-1\nFiles:\n".to_vec();
+    for (name, data) in &files {
+        fs::write(dir.join(name), data).unwrap();
+        writeln!(dsc, " {} {} {}", md5_hex(data), size(data.len()), name).unwrap();
+    }
+
+    fs::write(dir.join("hello_1.0-1.dsc"), dsc).unwrap();
+}
+
+fn unpack_dsc(size: impl Fn(usize) -> usize) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    source_package(dir.path(), size);
+    let options = Options {
+        debian_source: true,
+        ..Options::default()
+    };
+    let unpack =
+        Unpack::unpack_into_with(dir.path().join("hello_1.0-1.dsc"), dir.path(), &options).unwrap();
+    (dir, unpack)
+}
+
+#[test]
+fn assembled_tree() {
+    let (_dir, unpack) = unpack_dsc(|size| size);
+    let root = unpack.root().unwrap();
+
+    let mut tree: Vec<_> = root
+        .children
+        .entries()
+        .iter()
+        .map(|entry| {
+            (
+                String::from_utf8_lossy(&entry.local.path).to_string(),
+                fs::read(entry.local.temp.as_ref().unwrap()).unwrap(),
+            )
+        })
+        .collect();
+    tree.sort();
+
+    // upstream's top directory stripped, and its `debian/` replaced
+    let expected: Vec<(String, Vec<u8>)> = vec![
+        ("README".to_string(), b"hello, world\n".to_vec()),
+        ("debian/control".to_string(), b"Source: hello\n".to_vec()),
+        ("debian/rules".to_string(), b"#!/usr/bin/make -f\n".to_vec()),
+    ];
+    assert_eq!(expected, tree);
+}
+
+#[test]
+fn listed_size_is_checked() {
+    let (_dir, unpack) = unpack_dsc(|size| size + 1);
+    match unpack.status() {
+        Status::Error(e) => assert!(e.contains("size mismatch"), "{}", e),
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+#[test]
+fn listed_names_stay_next_to_the_dsc() {
+    let dir = tempfile::tempdir().unwrap();
+    let outside = b"root:*:19000:0:99999:7:::\n";
+    fs::write(dir.path().join("shadow"), outside).unwrap();
+    let source = dir.path().join("source");
+    fs::create_dir(&source).unwrap();
+
+    let line = |name: &str| format!(" {} {} {}\n", md5_hex(outside), outside.len(), name);
+    let escaping = dir.path().join("shadow").to_string_lossy().to_string();
+    for name in &["../shadow", escaping.as_str(), "..", "."] {
+        let dsc = format!(
+            "Format: 3.0 (quilt)\nSource: hello\nVersion: 1.0-1\nFiles:\n{}",
+            line(name)
+        );
+        fs::write(source.join("hello_1.0-1.dsc"), dsc).unwrap();
+
+        let options = Options {
+            debian_source: true,
+            ..Options::default()
+        };
+        let out = tempfile::tempdir().unwrap();
+        let unpack =
+            Unpack::unpack_into_with(source.join("hello_1.0-1.dsc"), out.path(), &options).unwrap();
+        match unpack.status() {
+            Status::Error(e) => assert!(e.contains("bad file name"), "{}: {}", name, e),
+            other => panic!("{}: unexpected status: {:?}", name, other),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::fs;
+
+use splayers::Details;
+use splayers::Endianness;
+use splayers::Executable;
+use splayers::ExecutableKind;
+use splayers::FileType;
+use splayers::Hardening;
+use splayers::Options;
+use splayers::Relro;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o755);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn unpack(files: &[(&str, &[u8])]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("binaries.tar");
+    fs::write(&path, tar(files)).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+/// What was found for each file, in order.
+fn executables(unpack: &Unpack) -> Vec<&Executable> {
+    unpack
+        .status()
+        .entries()
+        .iter()
+        .map(|entry| match entry.details {
+            Some(Details::Executable(ref executable)) => &**executable,
+            ref other => panic!("not an executable: {:?}", other),
+        })
+        .collect()
+}
+
+/// A little-endian x86_64 ELF, `ET_DYN`, with no sections: everything is found through
+/// the program headers, and one `PT_LOAD` maps the file at address zero.
+#[derive(Default)]
+struct Elf<'a> {
+    interpreter: Option<&'a str>,
+    soname: Option<&'a str>,
+    needed: &'a [&'a str],
+    runpath: Option<&'a str>,
+    exports: &'a [&'a str],
+    imports: &'a [&'a str],
+    bind_now: bool,
+    build_id: &'a [u8],
+}
+
+fn align(out: &mut Vec<u8>, to: usize) {
+    while !out.len().is_multiple_of(to) {
+        out.push(0);
+    }
+}
+
+fn string(strtab: &mut Vec<u8>, s: &str) -> u64 {
+    let at = strtab.len();
+    strtab.extend_from_slice(s.as_bytes());
+    strtab.push(0);
+    at as u64
+}
+
+impl Elf<'_> {
+    fn build(&self) -> Vec<u8> {
+        const MAX_PHDRS: usize = 6;
+        let mut out = vec![0u8; 64 + 56 * MAX_PHDRS];
+        let mut phdrs: Vec<(u32, u32, usize, usize)> = Vec::new();
+
+        if let Some(interpreter) = self.interpreter {
+            let at = out.len();
+            out.extend_from_slice(interpreter.as_bytes());
+            out.push(0);
+            phdrs.push((3, 4, at, out.len() - at));
+        }
+
+        if !self.build_id.is_empty() {
+            align(&mut out, 4);
+            let at = out.len();
+            out.extend_from_slice(&4u32.to_le_bytes());
+            out.extend_from_slice(&(self.build_id.len() as u32).to_le_bytes());
+            out.extend_from_slice(&3u32.to_le_bytes());
+            out.extend_from_slice(b"GNU\0");
+            out.extend_from_slice(self.build_id);
+            align(&mut out, 4);
+            phdrs.push((4, 4, at, out.len() - at));
+        }
+
+        let mut strtab = vec![0u8];
+        let mut dynamic: Vec<(u64, u64)> = Vec::new();
+        for needed in self.needed {
+            dynamic.push((1, string(&mut strtab, needed)));
+        }
+        if let Some(soname) = self.soname {
+            dynamic.push((14, string(&mut strtab, soname)));
+        }
+        if let Some(runpath) = self.runpath {
+            dynamic.push((29, string(&mut strtab, runpath)));
+        }
+
+        // null, then global functions: defined (absolute, as there are no sections), or not
+        let mut symtab = vec![0u8; 24];
+        for (names, shndx) in &[(self.exports, 0xfff1u16), (self.imports, 0)] {
+            for name in names.iter() {
+                symtab.extend_from_slice(&(string(&mut strtab, name) as u32).to_le_bytes());
+                symtab.push(0x12);
+                symtab.push(0);
+                symtab.extend_from_slice(&shndx.to_le_bytes());
+                symtab.extend_from_slice(&[0; 16]);
+            }
+        }
+        let symbols = symtab.len() / 24;
+
+        let strtab_at = out.len();
+        out.extend_from_slice(&strtab);
+        align(&mut out, 8);
+        let symtab_at = out.len();
+        out.extend_from_slice(&symtab);
+
+        // a single bucket; only the chain's length, the symbol count, matters
+        let hash_at = out.len();
+        for word in [1, symbols as u32, 0].iter().chain(&vec![0; symbols]) {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        align(&mut out, 8);
+
+        dynamic.extend_from_slice(&[
+            (5, strtab_at as u64),
+            (10, strtab.len() as u64),
+            (6, symtab_at as u64),
+            (11, 24),
+            (4, hash_at as u64),
+        ]);
+        if self.bind_now {
+            dynamic.push((24, 0));
+        }
+        dynamic.push((0, 0));
+
+        let dynamic_at = out.len();
+        for (tag, value) in &dynamic {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        let dynamic_len = out.len() - dynamic_at;
+
+        phdrs.extend_from_slice(&[
+            (1, 5, 0, out.len()),
+            (2, 6, dynamic_at, dynamic_len),
+            (0x6474_e551, 6, 0, 0),
+            (0x6474_e552, 4, dynamic_at, dynamic_len),
+        ]);
+        assert!(phdrs.len() <= MAX_PHDRS);
+
+        let mut header = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        header.extend_from_slice(&3u16.to_le_bytes());
+        header.extend_from_slice(&62u16.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&64u64.to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        for half in &[64u16, 56, phdrs.len() as u16, 64, 0, 0] {
+            header.extend_from_slice(&half.to_le_bytes());
+        }
+
+        for (kind, flags, offset, len) in phdrs {
+            header.extend_from_slice(&kind.to_le_bytes());
+            header.extend_from_slice(&flags.to_le_bytes());
+            for quad in &[offset, offset, offset, len, len, 8] {
+                header.extend_from_slice(&(*quad as u64).to_le_bytes());
+            }
+        }
+
+        out[..header.len()].copy_from_slice(&header);
+        out
+    }
+}
+
+/// A 64-bit x86_64 DLL: just the headers, with no sections or data directories.
+fn pe() -> Vec<u8> {
+    let mut pe = vec![0u8; 0x40];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3c..].copy_from_slice(&0x40u32.to_le_bytes());
+    pe.extend_from_slice(b"PE\0\0");
+
+    // COFF: x86_64, no sections, an optional header, an executable DLL
+    for half in &[0x8664u16, 0, 0, 0, 0, 0, 0, 0, 240, 0x2022] {
+        pe.extend_from_slice(&half.to_le_bytes());
+    }
+
+    let mut optional = vec![0u8; 240];
+    optional[..2].copy_from_slice(&0x20bu16.to_le_bytes());
+    optional[32..36].copy_from_slice(&0x1000u32.to_le_bytes());
+    optional[36..40].copy_from_slice(&0x200u32.to_le_bytes());
+    optional[56..60].copy_from_slice(&0x1000u32.to_le_bytes());
+    optional[60..64].copy_from_slice(&0x200u32.to_le_bytes());
+    optional[108..112].copy_from_slice(&16u32.to_le_bytes());
+    pe.extend_from_slice(&optional);
+
+    pe.resize(0x200, 0);
+    pe
+}
+
+const UUID: [u8; 16] = *b"\x01\x23\x45\x67\x89\xab\xcd\xef\x01\x23\x45\x67\x89\xab\xcd\xef";
+
+/// A 64-bit x86_64 Mach-O executable, with just a UUID and the dynamic linker.
+fn macho() -> Vec<u8> {
+    let mut macho = Vec::new();
+    for word in &[0xfeed_facfu32, 0x0100_0007, 3, 2, 2, 56, 0, 0] {
+        macho.extend_from_slice(&word.to_le_bytes());
+    }
+
+    macho.extend_from_slice(&0x1bu32.to_le_bytes());
+    macho.extend_from_slice(&24u32.to_le_bytes());
+    macho.extend_from_slice(&UUID);
+
+    macho.extend_from_slice(&0xeu32.to_le_bytes());
+    macho.extend_from_slice(&32u32.to_le_bytes());
+    macho.extend_from_slice(&12u32.to_le_bytes());
+    let mut name = b"/usr/lib/dyld".to_vec();
+    name.resize(20, 0);
+    macho.extend_from_slice(&name);
+    macho
+}
+
+/// An empty module: just the magic and version.
+const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+#[test]
+fn identified() {
+    let library = Elf {
+        soname: Some("libhello.so.1"),
+        ..Elf::default()
+    };
+
+    for (data, expected) in &[
+        (library.build(), FileType::Elf),
+        (pe(), FileType::Pe),
+        (macho(), FileType::MachO),
+        (WASM.to_vec(), FileType::Wasm),
+    ] {
+        assert_eq!(*expected, splayers::identify(data, None, None).file_type);
+    }
+
+    // a class file shares the fat Mach-O magic
+    assert_eq!(
+        FileType::JavaClass,
+        splayers::identify(b"\xca\xfe\xba\xbe\0\0\0\x34\0\0\0\0", None, None).file_type
+    );
+}
+
+#[test]
+fn elf_headline() {
+    let library = Elf {
+        soname: Some("libhello.so.1"),
+        build_id: b"\xde\xad\xbe\xef\x00\x01\x02\x03",
+        ..Elf::default()
+    };
+    let program = Elf {
+        interpreter: Some("/lib64/ld-linux-x86-64.so.2"),
+        ..Elf::default()
+    };
+
+    let (_dir, unpack) = unpack(&[
+        ("usr/lib/libhello.so.1", &library.build()),
+        ("usr/bin/hello", &program.build()),
+    ]);
+    let found = executables(&unpack);
+
+    let library = found[0];
+    assert_eq!("x86_64", library.architecture);
+    assert_eq!(Some(64), library.bits);
+    assert_eq!(Some(Endianness::Little), library.endianness);
+    assert_eq!(ExecutableKind::SharedLibrary, library.kind);
+    assert_eq!(None, library.interpreter);
+    assert_eq!(Some("libhello.so.1"), library.soname.as_deref());
+    assert_eq!(Some("deadbeef00010203"), library.build_id.as_deref());
+
+    // `ET_DYN` with a loader: a position-independent executable
+    let program = found[1];
+    assert_eq!(ExecutableKind::Executable, program.kind);
+    assert_eq!(
+        Some("/lib64/ld-linux-x86-64.so.2"),
+        program.interpreter.as_deref()
+    );
+    assert_eq!(None, program.build_id);
+}
+
+#[test]
+fn pe_macho_and_wasm_headlines() {
+    let (_dir, unpack) = unpack(&[
+        ("hello.dll", &pe()),
+        ("hello", &macho()),
+        ("hello.wasm", WASM),
+    ]);
+    let found = executables(&unpack);
+
+    let dll = found[0];
+    assert_eq!("x86_64", dll.architecture);
+    assert_eq!(Some(64), dll.bits);
+    assert_eq!(ExecutableKind::SharedLibrary, dll.kind);
+    assert!(dll.elf.is_none());
+
+    let macho = found[1];
+    assert_eq!("x86_64", macho.architecture);
+    assert_eq!(Some(64), macho.bits);
+    assert_eq!(Some(Endianness::Little), macho.endianness);
+    assert_eq!(ExecutableKind::Executable, macho.kind);
+    assert_eq!(Some("/usr/lib/dyld"), macho.interpreter.as_deref());
+    assert_eq!(
+        Some("0123456789abcdef0123456789abcdef"),
+        macho.build_id.as_deref()
+    );
+
+    let wasm = found[2];
+    assert_eq!("wasm32", wasm.architecture);
+    assert_eq!(Some(32), wasm.bits);
+}
+
+#[test]
+fn elf_dynamic_section_and_hardening() {
+    let library = Elf {
+        soname: Some("libhello.so.1"),
+        needed: &["libc.so.6"],
+        runpath: Some("$ORIGIN/../lib"),
+        exports: &["hello_greet", "hello_version"],
+        imports: &["puts", "__stack_chk_fail", "__printf_chk"],
+        bind_now: true,
+        ..Elf::default()
+    };
+    let program = Elf {
+        interpreter: Some("/lib64/ld-linux-x86-64.so.2"),
+        needed: &["libhello.so.1", "libc.so.6"],
+        imports: &["hello_greet"],
+        ..Elf::default()
+    };
+
+    let (_dir, unpack) = unpack(&[
+        ("usr/lib/libhello.so.1", &library.build()),
+        ("usr/bin/hello", &program.build()),
+    ]);
+    let found = executables(&unpack);
+
+    let library = found[0].elf.as_ref().expect("elf details");
+    assert_eq!(vec!["libc.so.6"], library.needed);
+    assert!(library.rpath.is_empty());
+    assert_eq!(vec!["$ORIGIN/../lib"], library.runpath);
+    assert_eq!(vec!["hello_greet", "hello_version"], library.exports);
+    assert_eq!(
+        vec!["puts", "__stack_chk_fail", "__printf_chk"],
+        library.imports
+    );
+    assert_eq!(
+        Hardening {
+            pie: false,
+            relro: Relro::Full,
+            nx: true,
+            stack_canary: true,
+            fortify: true,
+        },
+        library.hardening
+    );
+
+    let program = found[1].elf.as_ref().expect("elf details");
+    assert_eq!(vec!["libhello.so.1", "libc.so.6"], program.needed);
+    assert!(program.exports.is_empty());
+    assert_eq!(
+        Hardening {
+            pie: true,
+            relro: Relro::Partial,
+            nx: true,
+            stack_canary: false,
+            fortify: false,
+        },
+        program.hardening
+    );
+
+    // what the pair needs from elsewhere
+    assert_eq!(
+        vec!["libc.so.6"],
+        splayers::required_libraries(unpack.status().entries())
+    );
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io::Write;
+
+use splayers::GemInfo;
+use splayers::Options;
+use splayers::Package;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn unpack(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+fn gz(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// As `gem build` writes it.
+const METADATA: &[u8] = br#"--- !ruby/object:Gem::Specification
+name: hello
+version: !ruby/object:Gem::Version
+  version: 1.2.3
+platform: ruby
+authors:
+- Jane Doe
+- 'John Roe'
+autorequire:
+bindir: exe
+cert_chain: []
+date: 2023-01-01 00:00:00.000000000 Z
+dependencies:
+- !ruby/object:Gem::Dependency
+  name: rack
+  requirement: !ruby/object:Gem::Requirement
+    requirements:
+    - - ">="
+      - !ruby/object:Gem::Version
+        version: '2.0'
+    - - "<"
+      - !ruby/object:Gem::Version
+        version: '4'
+  type: :runtime
+  prerelease: false
+  version_requirements: !ruby/object:Gem::Requirement
+    requirements:
+    - - ">="
+      - !ruby/object:Gem::Version
+        version: '2.0'
+    - - "<"
+      - !ruby/object:Gem::Version
+        version: '4'
+- !ruby/object:Gem::Dependency
+  name: minitest
+  requirement: !ruby/object:Gem::Requirement
+    requirements:
+    - - "~>"
+      - !ruby/object:Gem::Version
+        version: '5.0'
+  type: :development
+  prerelease: false
+  version_requirements: !ruby/object:Gem::Requirement
+    requirements:
+    - - "~>"
+      - !ruby/object:Gem::Version
+        version: '5.0'
+description: Says hello, at length.
+email:
+- jane@example.org
+executables: []
+extensions: []
+extra_rdoc_files: []
+files:
+- lib/hello.rb
+homepage: https://example.org/hello
+licenses:
+- MIT
+- Apache-2.0
+metadata: {}
+post_install_message:
+rdoc_options: []
+require_paths:
+- lib
+required_ruby_version: !ruby/object:Gem::Requirement
+  requirements:
+  - - ">="
+    - !ruby/object:Gem::Version
+      version: '2.7'
+rubygems_version: 3.4.1
+signing_key:
+specification_version: 4
+summary: "Says hello"
+test_files: []
+"#;
+
+fn gem_info(unpack: &Unpack) -> Option<&GemInfo> {
+    match unpack.root().unwrap().package {
+        Some(Package::Gem(ref info)) => Some(info),
+        _ => None,
+    }
+}
+
+#[test]
+fn gem_metadata() {
+    let data = tar(&[
+        ("metadata.gz", &gz(METADATA)),
+        (
+            "data.tar.gz",
+            &gz(&tar(&[("lib/hello.rb", b"puts 'hello'\n")])),
+        ),
+        ("checksums.yaml.gz", &gz(b"---\n")),
+    ]);
+    let (_dir, unpack) = unpack("hello-1.2.3.gem", &data);
+    let info = gem_info(&unpack).expect("a gem");
+
+    assert_eq!("hello", info.name);
+    assert_eq!("1.2.3", info.version);
+    assert_eq!("Says hello", info.summary);
+    assert_eq!("https://example.org/hello", info.homepage);
+    assert_eq!(vec!["Jane Doe", "John Roe"], info.authors);
+    assert_eq!(vec!["MIT", "Apache-2.0"], info.licenses);
+
+    let dependencies: Vec<_> = info
+        .dependencies
+        .iter()
+        .map(|d| (d.name.as_str(), d.requirement.as_str(), d.runtime))
+        .collect();
+    assert_eq!(
+        vec![("rack", ">= 2.0, < 4", true), ("minitest", "~> 5.0", false)],
+        dependencies
+    );
+}
+
+#[test]
+fn tar_with_metadata_but_no_data_isnt_a_gem() {
+    let data = tar(&[("metadata.gz", &gz(METADATA))]);
+    let (_dir, unpack) = unpack("hello.tar", &data);
+    assert!(gem_info(&unpack).is_none());
+}
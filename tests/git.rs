@@ -0,0 +1,519 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use sha1::Digest;
+use splayers::GitView;
+use splayers::ItemType;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+type Id = [u8; 20];
+
+fn hex(id: &Id) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn id_of(kind: &str, data: &[u8]) -> Id {
+    let mut sha1 = sha1::Sha1::new();
+    sha1.update(format!("{} {}\0", kind, data.len()).as_bytes());
+    sha1.update(data);
+    sha1.finalize().into()
+}
+
+/// A tree, from its entries, which must be in git's order.
+fn tree(entries: &[(&str, &str, Id)]) -> Vec<u8> {
+    let mut tree = Vec::new();
+    for (mode, name, id) in entries {
+        tree.extend_from_slice(format!("{} {}\0", mode, name).as_bytes());
+        tree.extend_from_slice(id);
+    }
+    tree
+}
+
+fn commit(tree: &Id, parent: Option<&Id>, time: u64) -> Vec<u8> {
+    let parent = parent
+        .map(|parent| format!("parent {}\n", hex(parent)))
+        .unwrap_or_default();
+    format!(
+        "tree {}\n{}author A U Thor <author@example.org> {} +0000\n\
+         committer A U Thor <author@example.org> {} +0000\n\nA change\n",
+        hex(tree),
+        parent,
+        time,
+        time
+    )
+    .into_bytes()
+}
+
+/// `HEAD` on `main`, and somewhere for objects and refs.
+fn skeleton(git_dir: &Path) {
+    fs::create_dir_all(git_dir.join("objects/pack")).unwrap();
+    fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+    fs::create_dir_all(git_dir.join("refs/tags")).unwrap();
+    fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+}
+
+fn write_ref(git_dir: &Path, name: &str, id: &Id) {
+    fs::write(git_dir.join(name), format!("{}\n", hex(id))).unwrap();
+}
+
+/// Store an object loose, as its id.
+fn loose(git_dir: &Path, kind: &str, data: &[u8]) -> Id {
+    let id = id_of(kind, data);
+    let name = hex(&id);
+    let dir = git_dir.join("objects").join(&name[..2]);
+    fs::create_dir_all(&dir).unwrap();
+    let mut object = format!("{} {}\0", kind, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    fs::write(dir.join(&name[2..]), zlib(&object)).unwrap();
+    id
+}
+
+fn unpack_with(path: &Path, options: &Options) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let unpack = Unpack::unpack_into_with(path, dir.path(), options).unwrap();
+    (dir, unpack)
+}
+
+fn tree_view(name: &str) -> Options {
+    Options {
+        git: GitView::Tree(name.to_string()),
+        ..Options::default()
+    }
+}
+
+/// The path of each entry, and its content or link target.
+fn files(status: &Status) -> Vec<(String, Vec<u8>)> {
+    let entries = match status {
+        Status::Success(entries) => entries,
+        other => panic!("unexpected status: {:?}", other),
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let content = match entry.local.meta.item_type {
+                ItemType::SymbolicLink(ref target) => target.to_vec(),
+                _ => entry
+                    .local
+                    .temp
+                    .as_ref()
+                    .map(|temp| fs::read(temp).unwrap())
+                    .unwrap_or_default(),
+            };
+            (
+                String::from_utf8_lossy(&entry.local.path).into_owned(),
+                content,
+            )
+        })
+        .collect()
+}
+
+fn owned(files: &[(&str, &[u8])]) -> Vec<(String, Vec<u8>)> {
+    files
+        .iter()
+        .map(|(path, content)| (path.to_string(), content.to_vec()))
+        .collect()
+}
+
+/// Two commits, the first tagged `v1.0`, and a blob nothing refers to; all loose.
+struct Loose {
+    dir: tempfile::TempDir,
+    first: Id,
+    blobs: Vec<(Id, &'static [u8])>,
+}
+
+fn loose_repository() -> Loose {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = dir.path().join("project/.git");
+    skeleton(&git_dir);
+
+    let mut blobs: Vec<(Id, &'static [u8])> = Vec::new();
+    let mut blob = |content: &'static [u8]| {
+        let id = loose(&git_dir, "blob", content);
+        blobs.push((id, content));
+        id
+    };
+
+    let hi = blob(b"hi\n");
+    let first_tree = loose(&git_dir, "tree", &tree(&[("100644", "README", hi)]));
+    let first = loose(
+        &git_dir,
+        "commit",
+        &commit(&first_tree, None, 1_500_000_000),
+    );
+
+    let readme = blob(b"hello\n");
+    let empty = blob(b"");
+    let link = blob(b"README");
+    let script = blob(b"#!/bin/sh\necho hello\n");
+    let main = blob(b"fn main() {}\n");
+    blob(b"dangling\n");
+
+    let src = loose(&git_dir, "tree", &tree(&[("100644", "main.rs", main)]));
+    let second_tree = loose(
+        &git_dir,
+        "tree",
+        &tree(&[
+            ("100644", "README", readme),
+            ("100644", "empty", empty),
+            ("120000", "link", link),
+            ("100755", "run.sh", script),
+            ("40000", "src", src),
+            // a submodule's commit, which isn't in this repository
+            ("160000", "vendor", [0x42; 20]),
+        ]),
+    );
+    let second = loose(
+        &git_dir,
+        "commit",
+        &commit(&second_tree, Some(&first), 1_600_000_000),
+    );
+    write_ref(&git_dir, "refs/heads/main", &second);
+
+    let tag = format!(
+        "object {}\ntype commit\ntag v1.0\ntagger A U Thor <author@example.org> 1500000000 +0000\n\nThe first\n",
+        hex(&first)
+    );
+    let tag = loose(&git_dir, "tag", tag.as_bytes());
+    write_ref(&git_dir, "refs/tags/v1.0", &tag);
+
+    fs::write(dir.path().join("project/notes"), "not in the repository\n").unwrap();
+
+    Loose { dir, first, blobs }
+}
+
+const HEAD_FILES: &[(&str, &[u8])] = &[
+    ("README", b"hello\n"),
+    ("empty", b""),
+    ("link", b"README"),
+    ("run.sh", b"#!/bin/sh\necho hello\n"),
+    ("src/main.rs", b"fn main() {}\n"),
+];
+
+#[test]
+fn loose_objects() {
+    let repository = loose_repository();
+    let git_dir = repository.dir.path().join("project/.git");
+
+    let (_dir, unpack) = unpack_with(&git_dir, &Options::default());
+    assert_eq!(owned(HEAD_FILES), files(unpack.status()));
+
+    let entries = unpack.status().entries();
+    assert_eq!(ItemType::RegularFile, entries[0].local.meta.item_type);
+    assert_eq!(
+        ItemType::SymbolicLink(b"README".to_vec().into_boxed_slice()),
+        entries[2].local.meta.item_type
+    );
+}
+
+#[test]
+fn repository_in_a_directory() {
+    let repository = loose_repository();
+
+    let (_dir, unpack) = unpack_with(&repository.dir.path().join("project"), &Options::default());
+    let entries = unpack.status().entries();
+    let paths: Vec<&[u8]> = entries.iter().map(|entry| &entry.local.path[..]).collect();
+    assert_eq!(vec![&b"notes"[..], b".git"], paths);
+    assert_eq!(owned(HEAD_FILES), files(&entries[1].children));
+}
+
+#[test]
+fn views() {
+    let repository = loose_repository();
+    let git_dir = repository.dir.path().join("project/.git");
+    let first = owned(&[("README", b"hi\n")]);
+
+    // through an annotated tag, or by id
+    let (_dir, unpack) = unpack_with(&git_dir, &tree_view("v1.0"));
+    assert_eq!(first, files(unpack.status()));
+    let (_dir, unpack) = unpack_with(&git_dir, &tree_view(&hex(&repository.first)));
+    assert_eq!(first, files(unpack.status()));
+
+    let (_dir, unpack) = unpack_with(&git_dir, &tree_view("no-such-branch"));
+    assert!(matches!(unpack.status(), Status::Error(_)));
+
+    // every blob, referenced or not
+    let options = Options {
+        git: GitView::Blobs,
+        ..Options::default()
+    };
+    let (_dir, unpack) = unpack_with(&git_dir, &options);
+    let mut blobs: Vec<(String, Vec<u8>)> = repository
+        .blobs
+        .iter()
+        .map(|(id, content)| (hex(id), content.to_vec()))
+        .collect();
+    blobs.sort();
+    assert_eq!(blobs, files(unpack.status()));
+
+    // just files, of which loose objects are bare zlib streams
+    let options = Options {
+        git: GitView::Files,
+        ..Options::default()
+    };
+    let (_dir, unpack) = unpack_with(&git_dir, &options);
+    let entries = unpack.status().entries();
+    let paths: Vec<&[u8]> = entries.iter().map(|entry| &entry.local.path[..]).collect();
+    assert!(paths.contains(&&b"HEAD"[..]), "{:?}", paths);
+    assert!(paths.contains(&&b"refs/heads/main"[..]), "{:?}", paths);
+
+    let hello = hex(&repository.blobs[1].0);
+    let object = format!("objects/{}/{}", &hello[..2], &hello[2..]);
+    let object = entries
+        .iter()
+        .find(|entry| object.as_bytes() == &entry.local.path[..])
+        .expect("object listed");
+    assert_eq!(
+        vec![("..zlib".to_string(), b"blob 6\0hello\n".to_vec())],
+        files(&object.children)
+    );
+}
+
+/// The type and size of a pack entry.
+fn entry_header(kind: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![kind << 4 | (size & 0x0f) as u8];
+    let mut size = size >> 4;
+    while 0 != size {
+        *header.last_mut().unwrap() |= 0x80;
+        header.push((size & 0x7f) as u8);
+        size >>= 7;
+    }
+    header
+}
+
+/// An `OFS_DELTA`'s distance back, in which each continuation also adds one.
+fn distance(mut distance: usize) -> Vec<u8> {
+    let mut encoded = vec![(distance & 0x7f) as u8];
+    distance >>= 7;
+    while 0 != distance {
+        distance -= 1;
+        encoded.push(0x80 | (distance & 0x7f) as u8);
+        distance >>= 7;
+    }
+    encoded.reverse();
+    encoded
+}
+
+fn delta_size(mut size: usize, delta: &mut Vec<u8>) {
+    while size >= 0x80 {
+        delta.push(0x80 | (size & 0x7f) as u8);
+        size >>= 7;
+    }
+    delta.push(size as u8);
+}
+
+enum Op<'a> {
+    Copy(usize, usize),
+    Insert(&'a [u8]),
+}
+
+fn delta(base: &[u8], ops: &[Op]) -> Vec<u8> {
+    let result: usize = ops
+        .iter()
+        .map(|op| match *op {
+            Op::Copy(_, len) => len,
+            Op::Insert(data) => data.len(),
+        })
+        .sum();
+
+    let mut delta = Vec::new();
+    delta_size(base.len(), &mut delta);
+    delta_size(result, &mut delta);
+    for op in ops {
+        match *op {
+            // offsets and lengths of up to a byte
+            Op::Copy(offset, len) => {
+                let mut op = 0x80 | 0x10;
+                let mut args = Vec::new();
+                if 0 != offset {
+                    op |= 0x01;
+                    args.push(offset as u8);
+                }
+                args.push(len as u8);
+                delta.push(op);
+                delta.extend(args);
+            }
+            Op::Insert(data) => {
+                delta.push(data.len() as u8);
+                delta.extend_from_slice(data);
+            }
+        }
+    }
+    delta
+}
+
+struct Pack {
+    data: Vec<u8>,
+    /// The id and offset of each object, sorted by id.
+    index: Vec<(Id, u32)>,
+    head: Id,
+}
+
+const BASE: &[u8] = b"a line\na line\na line\na line\na line\na line\na line\nthe end\n";
+
+/// A commit of `a`, `b` and `c`: `b` an `OFS_DELTA` and `c` a `REF_DELTA` of `a`, which
+/// come before and after it, with a commit and tree between `a` and `b`.
+fn pack() -> Pack {
+    let more = [BASE, b"more\n"].concat();
+    let start = [&b"start\n"[..], &BASE[7..21]].concat();
+    let (a, b, c) = (
+        id_of("blob", BASE),
+        id_of("blob", &more),
+        id_of("blob", &start),
+    );
+    let tree = tree(&[("100644", "a", a), ("100644", "b", b), ("100644", "c", c)]);
+    let commit = commit(&id_of("tree", &tree), None, 1_600_000_000);
+
+    let mut data = b"PACK\0\0\0\x02\0\0\0\x05".to_vec();
+    let mut index = Vec::new();
+
+    index.push((c, data.len() as u32));
+    let ops = [Op::Insert(b"start\n"), Op::Copy(7, 14)];
+    let c_delta = delta(BASE, &ops);
+    data.extend(entry_header(7, c_delta.len()));
+    data.extend_from_slice(&a);
+    data.extend(zlib(&c_delta));
+
+    let a_offset = data.len();
+    index.push((a, a_offset as u32));
+    data.extend(entry_header(3, BASE.len()));
+    data.extend(zlib(BASE));
+
+    for (kind, content) in &[(2, &tree), (1, &commit)] {
+        let name = if 2 == *kind { "tree" } else { "commit" };
+        index.push((id_of(name, content), data.len() as u32));
+        data.extend(entry_header(*kind, content.len()));
+        data.extend(zlib(content));
+    }
+
+    let b_offset = data.len();
+    index.push((b, b_offset as u32));
+    let b_delta = delta(BASE, &[Op::Copy(0, BASE.len()), Op::Insert(b"more\n")]);
+    data.extend(entry_header(6, b_delta.len()));
+    data.extend(distance(b_offset - a_offset));
+    data.extend(zlib(&b_delta));
+
+    let mut sha1 = sha1::Sha1::new();
+    sha1.update(&data);
+    let checksum: Id = sha1.finalize().into();
+    data.extend_from_slice(&checksum);
+
+    index.sort();
+    Pack {
+        data,
+        index,
+        head: id_of("commit", &commit),
+    }
+}
+
+fn fanout(index: &[(Id, u32)]) -> Vec<u8> {
+    (0..=255u8)
+        .flat_map(|byte| {
+            let count = index.iter().filter(|(id, _)| id[0] <= byte).count() as u32;
+            count.to_be_bytes()
+        })
+        .collect()
+}
+
+fn idx_v1(index: &[(Id, u32)]) -> Vec<u8> {
+    let mut idx = fanout(index);
+    for (id, offset) in index {
+        idx.extend_from_slice(&offset.to_be_bytes());
+        idx.extend_from_slice(id);
+    }
+    idx.extend_from_slice(&[0; 40]);
+    idx
+}
+
+fn idx_v2(index: &[(Id, u32)]) -> Vec<u8> {
+    let mut idx = b"\xfftOc\0\0\0\x02".to_vec();
+    idx.extend(fanout(index));
+    for (id, _) in index {
+        idx.extend_from_slice(id);
+    }
+    // CRCs, which aren't checked
+    idx.extend(std::iter::repeat_n(0, 4 * index.len()));
+    for (_, offset) in index {
+        idx.extend_from_slice(&offset.to_be_bytes());
+    }
+    idx.extend_from_slice(&[0; 40]);
+    idx
+}
+
+const PACKED_FILES: &[(&str, &[u8])] = &[
+    ("a", BASE),
+    (
+        "b",
+        b"a line\na line\na line\na line\na line\na line\na line\nthe end\nmore\n",
+    ),
+    ("c", b"start\na line\na line\n"),
+];
+
+/// A repository with just the pack, and perhaps an index, with `main` in `packed-refs`.
+fn packed_repository(pack: &Pack, idx: Option<Vec<u8>>) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = dir.path();
+    skeleton(git_dir);
+    fs::write(git_dir.join("objects/pack/pack-1.pack"), &pack.data).unwrap();
+    if let Some(idx) = idx {
+        fs::write(git_dir.join("objects/pack/pack-1.idx"), idx).unwrap();
+    }
+    fs::write(
+        git_dir.join("packed-refs"),
+        format!(
+            "# pack-refs with: peeled\n{} refs/heads/main\n",
+            hex(&pack.head)
+        ),
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn packs_with_and_without_an_index() {
+    let pack = pack();
+    for idx in [Some(idx_v1(&pack.index)), Some(idx_v2(&pack.index)), None] {
+        let repository = packed_repository(&pack, idx);
+        let (_dir, unpack) = unpack_with(repository.path(), &Options::default());
+        assert_eq!(owned(PACKED_FILES), files(unpack.status()));
+    }
+}
+
+#[test]
+fn pack_on_its_own() {
+    let pack = pack();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("objects.pack");
+    fs::write(&path, &pack.data).unwrap();
+
+    let (_dir, unpack) = unpack_with(&path, &Options::default());
+    let mut blobs: Vec<(String, Vec<u8>)> = PACKED_FILES
+        .iter()
+        .map(|(_, content)| (hex(&id_of("blob", content)), content.to_vec()))
+        .collect();
+    blobs.sort();
+    assert_eq!(blobs, files(unpack.status()));
+}
+
+#[test]
+fn index_count_isnt_trusted() {
+    let pack = pack();
+    let mut idx = idx_v2(&pack.index);
+    idx.truncate(8 + 256 * 4);
+    idx[8 + 255 * 4..].copy_from_slice(&u32::MAX.to_be_bytes());
+
+    let repository = packed_repository(&pack, Some(idx));
+    let (_dir, unpack) = unpack_with(repository.path(), &Options::default());
+    match unpack.status() {
+        Status::Error(message) => assert!(message.contains("truncated"), "{}", message),
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
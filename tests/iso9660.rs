@@ -0,0 +1,476 @@
+use std::fs;
+
+use splayers::ItemType;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+const SECTOR: usize = 2048;
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// 2020-01-02 03:04:05, in GMT.
+const RECORDED: [u8; 7] = [120, 1, 2, 3, 4, 5, 0];
+
+fn both_endian_16(value: u16) -> Vec<u8> {
+    [value.to_le_bytes(), value.to_be_bytes()].concat()
+}
+
+fn both_endian_32(value: u32) -> Vec<u8> {
+    [value.to_le_bytes(), value.to_be_bytes()].concat()
+}
+
+/// A directory record; the name is padded to an even offset, and then the system use area.
+fn record((extent, size): (u32, u32), flags: u8, name: &[u8], system_use: &[u8]) -> Vec<u8> {
+    let mut record = vec![0u8; 33];
+    record[2..10].copy_from_slice(&both_endian_32(extent));
+    record[10..18].copy_from_slice(&both_endian_32(size));
+    record[18..25].copy_from_slice(&RECORDED);
+    record[25] = flags;
+    record[28..32].copy_from_slice(&both_endian_16(1));
+    record[32] = name.len() as u8;
+    record.extend_from_slice(name);
+    if name.len().is_multiple_of(2) {
+        record.push(0);
+    }
+    record.extend_from_slice(system_use);
+    if 1 == record.len() % 2 {
+        record.push(0);
+    }
+    record[0] = record.len() as u8;
+    record
+}
+
+/// Records don't cross sectors: one which doesn't fit starts the next.
+fn pack(records: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for record in records {
+        let used = data.len() % SECTOR;
+        if used + record.len() > SECTOR {
+            data.resize(data.len() + SECTOR - used, 0);
+        }
+        data.extend_from_slice(record);
+    }
+    data.resize(data.len().div_ceil(SECTOR) * SECTOR, 0);
+    data
+}
+
+fn susp(signature: &[u8; 2], data: &[u8]) -> Vec<u8> {
+    [&signature[..], &[4 + data.len() as u8, 1], data].concat()
+}
+
+/// SUSP is in use, and its entries start straight after the name; which extension uses it.
+fn sp_er() -> Vec<u8> {
+    let er = [&[10, 0, 0, 1][..], b"RRIP_1991A"].concat();
+    [susp(b"SP", &[0xbe, 0xef, 0]), susp(b"ER", &er)].concat()
+}
+
+fn nm(name: &str) -> Vec<u8> {
+    susp(b"NM", &[&[0], name.as_bytes()].concat())
+}
+
+fn px(mode: u32) -> Vec<u8> {
+    let fields = [mode, 1, 1000, 100];
+    susp(
+        b"PX",
+        &fields
+            .iter()
+            .flat_map(|&field| both_endian_32(field))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Just the modification time, 2021-06-07 08:09:10.
+fn tf() -> Vec<u8> {
+    susp(b"TF", &[0x02, 121, 6, 7, 8, 9, 10, 0])
+}
+
+fn sl(components: &[&str]) -> Vec<u8> {
+    let mut data = vec![0];
+    for component in components {
+        data.extend_from_slice(&[0, component.len() as u8]);
+        data.extend_from_slice(component.as_bytes());
+    }
+    susp(b"SL", &data)
+}
+
+fn pn(major: u32, minor: u32) -> Vec<u8> {
+    susp(
+        b"PN",
+        &[both_endian_32(major), both_endian_32(minor)].concat(),
+    )
+}
+
+enum Content {
+    /// The extents of a file: none if it's empty.
+    File(Vec<Vec<u8>>),
+    Directory(Vec<Node>),
+}
+
+struct Node {
+    name: Vec<u8>,
+    system_use: Vec<u8>,
+    content: Content,
+}
+
+impl Node {
+    fn records(&self) -> usize {
+        match self.content {
+            Content::File(ref extents) => extents.len().max(1),
+            Content::Directory(_) => 1,
+        }
+    }
+}
+
+struct Builder {
+    image: Vec<u8>,
+}
+
+impl Builder {
+    fn reserve(&mut self, len: usize) -> u32 {
+        let sector = self.image.len() / SECTOR;
+        let len = len.div_ceil(SECTOR).max(1) * SECTOR;
+        self.image.resize(self.image.len() + len, 0);
+        sector as u32
+    }
+
+    fn write(&mut self, sector: u32, data: &[u8]) {
+        let at = sector as usize * SECTOR;
+        self.image[at..at + data.len()].copy_from_slice(data);
+    }
+
+    /// Write a directory, after everything in it, as its extent and size.
+    fn directory(&mut self, nodes: &[Node], parent: Option<(u32, u32)>, dot: &[u8]) -> (u32, u32) {
+        let unplaced: Vec<Vec<(u32, u32)>> = nodes
+            .iter()
+            .map(|node| vec![(0, 0); node.records()])
+            .collect();
+        let len = pack(&records(nodes, &unplaced, (0, 0), (0, 0), dot)).len();
+        let itself = (self.reserve(len), len as u32);
+
+        let mut placed = Vec::new();
+        for node in nodes {
+            placed.push(match node.content {
+                Content::File(ref extents) if extents.is_empty() => vec![(0, 0)],
+                Content::File(ref extents) => extents
+                    .iter()
+                    .map(|extent| {
+                        let sector = self.reserve(extent.len());
+                        self.write(sector, extent);
+                        (sector, extent.len() as u32)
+                    })
+                    .collect(),
+                Content::Directory(ref children) => {
+                    vec![self.directory(children, Some(itself), &[])]
+                }
+            });
+        }
+
+        let data = pack(&records(
+            nodes,
+            &placed,
+            itself,
+            parent.unwrap_or(itself),
+            dot,
+        ));
+        self.write(itself.0, &data);
+        itself
+    }
+}
+
+/// `.`, `..`, then each node's records; all but the last of a file's flagged as continuing.
+fn records(
+    nodes: &[Node],
+    placed: &[Vec<(u32, u32)>],
+    itself: (u32, u32),
+    parent: (u32, u32),
+    dot: &[u8],
+) -> Vec<Vec<u8>> {
+    let mut records = vec![
+        record(itself, FLAG_DIRECTORY, &[0], dot),
+        record(parent, FLAG_DIRECTORY, &[1], &[]),
+    ];
+    for (node, placed) in nodes.iter().zip(placed) {
+        let directory = match node.content {
+            Content::File(_) => 0,
+            Content::Directory(_) => FLAG_DIRECTORY,
+        };
+        for (i, &at) in placed.iter().enumerate() {
+            let more = if i + 1 < placed.len() {
+                FLAG_MULTI_EXTENT
+            } else {
+                0
+            };
+            records.push(record(at, directory | more, &node.name, &node.system_use));
+        }
+    }
+    records
+}
+
+/// Path tables listing just the root, little and big endian, which we don't read.
+fn path_tables(root: u32) -> [Vec<u8>; 2] {
+    let table =
+        |extent: [u8; 4], parent: [u8; 2]| [&[1, 0][..], &extent, &parent, &[0, 0]].concat();
+    [
+        table(root.to_le_bytes(), 1u16.to_le_bytes()),
+        table(root.to_be_bytes(), 1u16.to_be_bytes()),
+    ]
+}
+
+/// A volume descriptor; its path tables are at `tables` and the sector after.
+fn descriptor(kind: u8, root: (u32, u32), tables: u32, sectors: usize) -> Vec<u8> {
+    let mut descriptor = vec![0u8; SECTOR];
+    descriptor[0] = kind;
+    descriptor[1..6].copy_from_slice(b"CD001");
+    descriptor[6] = 1;
+    if 255 == kind {
+        return descriptor;
+    }
+    descriptor[80..88].copy_from_slice(&both_endian_32(sectors as u32));
+    if 2 == kind {
+        // Joliet, UCS-2 level 3
+        descriptor[88..91].copy_from_slice(b"%/E");
+    }
+    descriptor[120..124].copy_from_slice(&both_endian_16(1));
+    descriptor[124..128].copy_from_slice(&both_endian_16(1));
+    descriptor[128..132].copy_from_slice(&both_endian_16(SECTOR as u16));
+    descriptor[132..140].copy_from_slice(&both_endian_32(10));
+    descriptor[140..144].copy_from_slice(&tables.to_le_bytes());
+    descriptor[148..152].copy_from_slice(&(tables + 1).to_be_bytes());
+    descriptor[156..190].copy_from_slice(&record(root, FLAG_DIRECTORY, &[0], &[]));
+    descriptor[881] = 1;
+    descriptor
+}
+
+const README: &[u8] = b"Read me.\n";
+
+fn big() -> Vec<Vec<u8>> {
+    let first = (0..2 * SECTOR).map(|i| (i % 251) as u8).collect();
+    vec![first, b"the end\n".to_vec()]
+}
+
+/// Enough of them that `docs` takes two sectors.
+fn notes(name: impl Fn(usize) -> (Vec<u8>, Vec<u8>)) -> Vec<Node> {
+    (1..=30)
+        .map(|i| {
+            let (name, system_use) = name(i);
+            Node {
+                name,
+                system_use,
+                content: Content::File(vec![format!("note {}\n", i).into_bytes()]),
+            }
+        })
+        .collect()
+}
+
+/// The ISO 9660 names, and, if there's Rock Ridge, the real ones, and some special files.
+fn iso_tree(rock_ridge: bool) -> Vec<Node> {
+    let su = |parts: &[Vec<u8>]| -> Vec<u8> {
+        if rock_ridge {
+            parts.concat()
+        } else {
+            Vec::new()
+        }
+    };
+    let node = |name: &str, system_use: Vec<u8>, content| Node {
+        name: name.as_bytes().to_vec(),
+        system_use,
+        content,
+    };
+
+    let mut tree = vec![
+        node(
+            "BIG.DAT;1",
+            su(&[nm("big.dat"), px(0o100_644)]),
+            Content::File(big()),
+        ),
+        node(
+            "DOCS",
+            su(&[nm("docs"), px(0o040_755)]),
+            Content::Directory(notes(|i| {
+                (
+                    format!("NOTE{:02}.TXT;1", i).into_bytes(),
+                    su(&[nm(&format!("note-{:02}.txt", i)), px(0o100_644)]),
+                )
+            })),
+        ),
+    ];
+
+    if rock_ridge {
+        tree.extend(vec![
+            node(
+                "FIFO.;1",
+                su(&[nm("fifo"), px(0o010_644)]),
+                Content::File(Vec::new()),
+            ),
+            node(
+                "LINK.;1",
+                su(&[nm("link"), px(0o120_777), sl(&["docs", "note-01.txt"])]),
+                Content::File(Vec::new()),
+            ),
+            node(
+                "NULL.;1",
+                su(&[nm("null"), px(0o020_666), pn(1, 3)]),
+                Content::File(Vec::new()),
+            ),
+        ]);
+    }
+
+    tree.push(node(
+        "README.TXT;1",
+        su(&[nm("ReadMe.txt"), px(0o100_644), tf()]),
+        Content::File(vec![README.to_vec()]),
+    ));
+    tree
+}
+
+fn ucs2(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+fn joliet_tree() -> Vec<Node> {
+    let node = |name: &str, content| Node {
+        name: ucs2(name),
+        system_use: Vec::new(),
+        content,
+    };
+    vec![
+        node("big.dat;1", Content::File(big())),
+        node(
+            "docs",
+            Content::Directory(notes(|i| {
+                (ucs2(&format!("note-{:02}.txt;1", i)), Vec::new())
+            })),
+        ),
+        node("ReadMe.txt;1", Content::File(vec![README.to_vec()])),
+    ]
+}
+
+fn image(rock_ridge: bool, joliet: bool) -> Vec<u8> {
+    // the system area, the volume descriptors, and two pairs of path tables
+    let mut builder = Builder {
+        image: vec![0; 23 * SECTOR],
+    };
+    let dot = if rock_ridge {
+        [sp_er(), px(0o040_755)].concat()
+    } else {
+        Vec::new()
+    };
+    let primary = builder.directory(&iso_tree(rock_ridge), None, &dot);
+    let joliet = if joliet {
+        Some(builder.directory(&joliet_tree(), None, &[]))
+    } else {
+        None
+    };
+
+    let sectors = builder.image.len() / SECTOR;
+    let mut descriptors = vec![descriptor(1, primary, 19, sectors)];
+    descriptors.extend(joliet.map(|root| descriptor(2, root, 21, sectors)));
+    descriptors.push(descriptor(255, (0, 0), 0, sectors));
+    builder.write(16, &descriptors.concat());
+
+    for (tables, root) in [(19, Some(primary)), (21, joliet)] {
+        if let Some((extent, _)) = root {
+            let [little, big] = path_tables(extent);
+            builder.write(tables, &little);
+            builder.write(tables + 1, &big);
+        }
+    }
+    builder.image
+}
+
+fn unpack(data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("disc.iso");
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+/// The path of each entry, and its content or link target.
+fn files(status: &Status) -> Vec<(String, Vec<u8>)> {
+    status
+        .entries()
+        .iter()
+        .map(|entry| {
+            let content = match entry.local.meta.item_type {
+                ItemType::SymbolicLink(ref target) => target.to_vec(),
+                _ => entry
+                    .local
+                    .temp
+                    .as_ref()
+                    .map(|temp| fs::read(temp).unwrap())
+                    .unwrap_or_default(),
+            };
+            (
+                String::from_utf8_lossy(&entry.local.path).into_owned(),
+                content,
+            )
+        })
+        .collect()
+}
+
+/// The files either kind of name should find, as named.
+fn expected(big: &str, docs: &str, note: impl Fn(usize) -> String) -> Vec<(String, Vec<u8>)> {
+    let mut expected = vec![
+        (big.to_string(), self::big().concat()),
+        (format!("{}/", docs), Vec::new()),
+    ];
+    for i in 1..=30 {
+        expected.push((
+            format!("{}/{}", docs, note(i)),
+            format!("note {}\n", i).into_bytes(),
+        ));
+    }
+    expected
+}
+
+#[test]
+fn rock_ridge() {
+    let (_dir, unpack) = unpack(&image(true, true));
+    assert!(matches!(unpack.status(), Status::Success(_)));
+
+    let mut want = expected("big.dat", "docs", |i| format!("note-{:02}.txt", i));
+    want.extend(vec![
+        ("fifo".to_string(), Vec::new()),
+        ("link".to_string(), b"docs/note-01.txt".to_vec()),
+        ("null".to_string(), Vec::new()),
+        ("ReadMe.txt".to_string(), README.to_vec()),
+    ]);
+    assert_eq!(want, files(unpack.status()));
+
+    let entries = unpack.status().entries();
+    let meta = |path: &str| {
+        let entry = entries
+            .iter()
+            .find(|entry| path.as_bytes() == &entry.local.path[..])
+            .unwrap();
+        &entry.local.meta
+    };
+    assert_eq!(ItemType::Directory, meta("docs/").item_type);
+    assert_eq!(ItemType::Fifo, meta("fifo").item_type);
+    assert_eq!(
+        ItemType::CharacterDevice { major: 1, minor: 3 },
+        meta("null").item_type
+    );
+    assert_eq!(ItemType::RegularFile, meta("ReadMe.txt").item_type);
+
+    // only the readme has a `TF`
+    assert_ne!(meta("fifo").mtime, meta("ReadMe.txt").mtime);
+}
+
+#[test]
+fn joliet() {
+    let (_dir, unpack) = unpack(&image(false, true));
+    let mut want = expected("big.dat", "docs", |i| format!("note-{:02}.txt", i));
+    want.push(("ReadMe.txt".to_string(), README.to_vec()));
+    assert_eq!(want, files(unpack.status()));
+}
+
+#[test]
+fn plain_names() {
+    let (_dir, unpack) = unpack(&image(false, false));
+    let mut want = expected("BIG.DAT", "DOCS", |i| format!("NOTE{:02}.TXT", i));
+    want.push(("README.TXT".to_string(), README.to_vec()));
+    assert_eq!(want, files(unpack.status()));
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use splayers::ClassVersion;
+use splayers::Details;
+use splayers::FileType;
+use splayers::JavaInfo;
+use splayers::JavaKind;
+use splayers::MavenCoordinates;
+use splayers::Options;
+use splayers::Package;
+use splayers::Unpack;
+use splayers::ZipKind;
+
+fn unpack(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, data) in files {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn java_info(unpack: &Unpack) -> Option<&JavaInfo> {
+    match unpack.root().unwrap().package {
+        Some(Package::Java(ref info)) => Some(info),
+        _ => None,
+    }
+}
+
+/// The class file header, with enough of a constant pool to not be tiny.
+fn class(major: u16) -> Vec<u8> {
+    let mut class = b"\xca\xfe\xba\xbe\0\0".to_vec();
+    class.extend_from_slice(&major.to_be_bytes());
+    class.extend_from_slice(&[0; 24]);
+    class
+}
+
+const MANIFEST: &[u8] = b"Manifest-Version: 1.0\r
+Main-Class: com.example.hello.Main\r
+Class-Path: lib/a-very-long-dependency-name-which-needs-wrapping-1.0.ja\r
+ r lib/another.jar\r
+\r
+Name: com/example/hello/\r
+Sealed: true\r
+";
+
+const POM_PROPERTIES: &[u8] = b"#Generated by Maven
+#Thu Jan 01 00:00:00 UTC 1970
+groupId=com.example
+artifactId = hello
+version: 1.0
+";
+
+#[test]
+fn jar_manifest_and_maven_coordinates() {
+    let data = zip(&[
+        ("META-INF/MANIFEST.MF", MANIFEST),
+        (
+            "META-INF/maven/com.example/hello/pom.properties",
+            POM_PROPERTIES,
+        ),
+        ("com/example/hello/Main.class", &class(52)),
+    ]);
+    let (_dir, unpack) = unpack("hello-1.0.jar", &data);
+    let info = java_info(&unpack).expect("a jar");
+
+    assert_eq!(JavaKind::Jar, info.kind);
+    assert_eq!(Some("com.example.hello.Main"), info.attribute("main-class"));
+    assert_eq!(
+        Some("lib/a-very-long-dependency-name-which-needs-wrapping-1.0.jar lib/another.jar"),
+        info.attribute("Class-Path")
+    );
+    // only the main section
+    assert_eq!(None, info.attribute("Sealed"));
+    assert_eq!(
+        vec![MavenCoordinates {
+            group_id: "com.example".to_string(),
+            artifact_id: "hello".to_string(),
+            version: "1.0".to_string(),
+        }],
+        info.maven
+    );
+}
+
+#[test]
+fn class_versions() {
+    let data = zip(&[
+        ("META-INF/MANIFEST.MF", MANIFEST),
+        ("Old.class", &class(48)),
+        ("Eight.class", &class(52)),
+        ("Seventeen.class", &class(61)),
+    ]);
+    let (_dir, unpack) = unpack("hello-1.0.jar", &data);
+
+    let releases: Vec<_> = unpack.root().unwrap().children.entries()[1..]
+        .iter()
+        .map(|entry| match entry.details {
+            Some(Details::JavaClass(version)) => version.java_release(),
+            ref other => panic!("no class version: {:?}", other),
+        })
+        .collect();
+    assert_eq!(vec!["1.4", "8", "17"], releases);
+
+    assert_eq!(
+        "1.1",
+        ClassVersion {
+            major: 45,
+            minor: 3
+        }
+        .java_release()
+    );
+}
+
+#[test]
+fn war() {
+    let data = zip(&[
+        ("index.jsp", b"<html></html>\n"),
+        ("WEB-INF/web.xml", b"<web-app/>\n"),
+        ("WEB-INF/classes/Hello.class", &class(52)),
+    ]);
+    let (_dir, unpack) = unpack("hello.war", &data);
+    assert_eq!(JavaKind::War, java_info(&unpack).expect("a war").kind);
+}
+
+#[test]
+fn stray_web_inf_isnt_a_war() {
+    let data = zip(&[("docs/README", b"hello\n"), ("WEB-INF/notes.txt", b"hi\n")]);
+    let (_dir, unpack) = unpack("backup.zip", &data);
+    assert!(java_info(&unpack).is_none());
+}
+
+#[test]
+fn ear() {
+    let data = zip(&[
+        ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\n"),
+        ("META-INF/application.xml", b"<application/>\n"),
+        ("hello.war", &zip(&[("WEB-INF/web.xml", b"<web-app/>\n")])),
+    ]);
+    let (_dir, unpack) = unpack("hello.ear", &data);
+    let root = unpack.root().unwrap();
+    assert_eq!(JavaKind::Ear, java_info(&unpack).expect("an ear").kind);
+
+    match root.children.entries()[2].package {
+        Some(Package::Java(ref info)) => assert_eq!(JavaKind::War, info.kind),
+        ref other => panic!("not a war: {:?}", other),
+    }
+}
+
+#[test]
+fn zip_kind_isnt_just_the_first_member() {
+    let data = zip(&[
+        ("com/example/Hello.class", &class(52)),
+        ("META-INF/MANIFEST.MF", MANIFEST),
+    ]);
+    assert_eq!(
+        FileType::Zip(ZipKind::Java),
+        splayers::identify(&data, None, None).file_type
+    );
+
+    let data = zip(&[("docs/README", b"hello\n"), ("src/main.c", b"int main;\n")]);
+    assert_eq!(
+        FileType::Zip(ZipKind::Plain),
+        splayers::identify(&data, None, None).file_type
+    );
+}
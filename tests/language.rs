@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::Write;
+
+use splayers::Classification;
+use splayers::Language;
+use splayers::Options;
+use splayers::Unpack;
+use splayers::Verdict;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+/// The classification of each file, in order.
+fn classify(files: &[(&str, &[u8])]) -> Vec<Classification> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("source.tar");
+    fs::write(&path, tar(files)).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    unpack
+        .status()
+        .entries()
+        .iter()
+        .map(|entry| entry.classification.expect("classified"))
+        .collect()
+}
+
+fn languages(files: &[(&str, &[u8])]) -> Vec<Option<Language>> {
+    classify(files)
+        .into_iter()
+        .map(|classification| classification.language)
+        .collect()
+}
+
+#[test]
+fn by_name_and_shebang() {
+    assert_eq!(
+        vec![
+            Some(Language::Rust),
+            Some(Language::Makefile),
+            Some(Language::Cpp),
+            Some(Language::C),
+            Some(Language::Python),
+            Some(Language::Shell),
+            None,
+        ],
+        languages(&[
+            ("src/lib.rs", b"pub mod thing;\n"),
+            ("Makefile", b"all:\n\ttrue\n"),
+            ("widget.h", b"namespace widgets {\nclass Widget;\n}\n"),
+            ("widget.c", b"int widget(void) { return 0; }\n"),
+            ("bin/tool", b"#!/usr/bin/env python3 -u\nprint('hi')\n"),
+            ("bin/run", b"#! /bin/sh -e\nexec tool\n"),
+            ("bin/other", b"#!/usr/bin/frobnicate\n"),
+        ])
+    );
+}
+
+#[test]
+fn by_content() {
+    assert_eq!(
+        vec![
+            Some(Language::Rust),
+            Some(Language::Python),
+            Some(Language::Go),
+            Some(Language::Html),
+        ],
+        languages(&[
+            (
+                "greeter",
+                b"struct Greeter;\n\nimpl Greeter {\n    fn hi(&self) {}\n}\n",
+            ),
+            (
+                "tool",
+                b"import os\n\n\ndef main(args):\n    print(os.getcwd())\n",
+            ),
+            ("server", b"package main\n\nfunc main() {}\n"),
+            ("page", b"<!DOCTYPE html>\n<html></html>\n"),
+        ])
+    );
+}
+
+#[test]
+fn by_content_isnt_fooled() {
+    assert_eq!(
+        vec![None, None, None],
+        languages(&[
+            (
+                "NOTES",
+                b"Design notes.\nimpl details are in the second chapter.\n",
+            ),
+            (
+                "twice",
+                b"@main def run(): Unit =\n  println(twice(2))\n\ndef twice(x: Int): Int = x * 2\n",
+            ),
+            (
+                "hello",
+                b"class Hello\n  def greet(name)\n    puts \"hi #{name}\"\n  end\nend\n",
+            ),
+        ])
+    );
+}
+
+#[test]
+fn verdicts() {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(b"hello\n").unwrap();
+    let minified = format!("var a={};", "1+".repeat(2048) + "1");
+
+    let verdicts: Vec<Verdict> = classify(&[
+        ("README", b"Says hello.\n"),
+        ("package-lock.json", b"{\"lockfileVersion\": 3}\n"),
+        ("jquery.min.js", b"!function(){}();\n"),
+        ("bundle.js", minified.as_bytes()),
+        (
+            "api.pb.go",
+            b"// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n",
+        ),
+        ("hello.txt.gz", &gz.finish().unwrap()),
+    ])
+    .into_iter()
+    .map(|classification| classification.verdict)
+    .collect();
+
+    assert_eq!(
+        vec![
+            Verdict::Text,
+            Verdict::Generated,
+            Verdict::Generated,
+            Verdict::Generated,
+            Verdict::Generated,
+            Verdict::Binary,
+        ],
+        verdicts
+    );
+}
@@ -0,0 +1,150 @@
+use std::fs;
+
+use splayers::License;
+use splayers::LicenseSource;
+use splayers::Options;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+/// The licence found for each file, in order.
+fn licenses(files: &[(&str, &[u8])]) -> Vec<Option<License>> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("source.tar");
+    fs::write(&path, tar(files)).unwrap();
+    let options = Options {
+        licenses: true,
+        ..Options::default()
+    };
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &options).unwrap();
+    unpack
+        .status()
+        .entries()
+        .iter()
+        .map(|entry| entry.license.clone())
+        .collect()
+}
+
+fn tagged(expression: &str) -> Option<License> {
+    Some(License {
+        expression: expression.to_string(),
+        source: LicenseSource::Tag,
+    })
+}
+
+#[test]
+fn tags_in_comments() {
+    assert_eq!(
+        vec![
+            tagged("MIT"),
+            tagged("Apache-2.0 OR MIT"),
+            tagged("LGPL-2.1-only WITH OCaml-LGPL-linking-exception"),
+            tagged("(GPL-2.0-or-later AND BSD-3-Clause)"),
+            tagged("LicenseRef-Proprietary"),
+        ],
+        licenses(&[
+            (
+                "hello.c",
+                b"/* SPDX-License-Identifier: MIT */\nint main() { return 0; }\n",
+            ),
+            (
+                "index.html",
+                b"<!-- SPDX-License-Identifier: Apache-2.0 OR MIT -->\n<html></html>\n",
+            ),
+            (
+                "main.ml",
+                b"(* SPDX-License-Identifier: LGPL-2.1-only WITH OCaml-LGPL-linking-exception *)\nlet () = ()\n",
+            ),
+            (
+                "hello.sh",
+                b"#!/bin/sh\n# SPDX-License-Identifier: (GPL-2.0-or-later AND BSD-3-Clause)\necho hello\n",
+            ),
+            (
+                "hello.rs",
+                b"// SPDX-License-Identifier: LicenseRef-Proprietary\nfn main() {}\n",
+            ),
+        ])
+    );
+}
+
+#[test]
+fn tag_in_a_string_literal() {
+    assert_eq!(
+        vec![None, tagged("MIT")],
+        licenses(&[
+            (
+                "scan.py",
+                b"TAG = \"SPDX-License-Identifier: \"\n\ndef scan(line):\n    return TAG in line\n",
+            ),
+            (
+                "scan.c",
+                b"static const char *tag = \"SPDX-License-Identifier:\";\n// SPDX-License-Identifier: MIT\n",
+            ),
+        ])
+    );
+}
+
+const MIT: &[u8] = b"Copyright (c) 2020 Someone
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the \"Software\"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software.
+";
+
+const GPL_NOTICE: &[u8] = b"/*
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ */
+int main() { return 0; }
+";
+
+/// As the GPL's full text starts, and mentions the LGPL later on.
+const GPL_TEXT: &[u8] = b"                    GNU GENERAL PUBLIC LICENSE
+                       Version 2, June 1991
+
+ Copyright (C) 1989, 1991 Free Software Foundation, Inc.
+
+  ... you may use the GNU Library General Public License instead ...
+";
+
+#[test]
+fn full_texts_and_notices() {
+    let found = |expression: &str, source| {
+        Some(License {
+            expression: expression.to_string(),
+            source,
+        })
+    };
+
+    assert_eq!(
+        vec![
+            found("MIT", LicenseSource::LicenseFile),
+            found("GPL-2.0-or-later", LicenseSource::Header),
+            found("GPL-2.0-only", LicenseSource::LicenseFile),
+            None,
+        ],
+        licenses(&[
+            ("LICENSE", MIT),
+            ("main.c", GPL_NOTICE),
+            ("COPYING", GPL_TEXT),
+            ("README", b"Says hello.\n"),
+        ])
+    );
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::io::Write;
+
+use md5::Digest;
+use splayers::Options;
+use splayers::Package;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn unpack(name: &str, data: &[u8], options: &Options) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), options).unwrap();
+    (dir, unpack)
+}
+
+fn gz(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn md5sums(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut md5sums = Vec::new();
+    for (path, data) in files {
+        let digest = md5::Md5::digest(data);
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        writeln!(md5sums, "{}  {}", hex, path.trim_start_matches("./")).unwrap();
+    }
+    md5sums
+}
+
+/// A `.deb` with a README, and a gzipped man page, which will be unpacked in turn.
+fn hello_deb() -> Vec<u8> {
+    let man = gz(b".TH HELLO 1\n");
+    let files: &[(&str, &[u8])] = &[
+        ("./usr/share/doc/hello/README", b"hello, world\n"),
+        ("./usr/share/man/man1/hello.1.gz", &man),
+    ];
+
+    let control = tar(&[
+        (
+            "./control",
+            b"Package: hello\nVersion: 1.0-1\nArchitecture: all\n",
+        ),
+        ("./md5sums", &md5sums(files)),
+    ]);
+    let data = tar(files);
+
+    let mut deb = ar::Builder::new(Vec::new());
+    for (name, content) in &[
+        ("debian-binary", &b"2.0\n"[..]),
+        ("control.tar", &control[..]),
+        ("data.tar", &data[..]),
+    ] {
+        let header = ar::Header::new(name.as_bytes().to_vec(), content.len() as u64);
+        deb.append(&header, *content).unwrap();
+    }
+    deb.into_inner().unwrap()
+}
+
+#[test]
+fn lone_deb() {
+    let (_dir, unpack) = unpack("hello_1.0-1_all.deb", &hello_deb(), &Options::default());
+    let root = unpack.root().expect("a single file has a root");
+    assert_eq!(&b"hello_1.0-1_all.deb"[..], &root.local.path[..]);
+    match root.package {
+        Some(Package::Deb(ref info)) => {
+            assert_eq!("hello", info.control.package);
+            assert_eq!("1.0-1", info.control.version);
+        }
+        ref other => panic!("not a deb: {:?}", other),
+    }
+
+    // the man page was unpacked, and its content thrown away
+    let report = splayers::verify_md5sums(root).expect("md5sums");
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(vec!["usr/share/man/man1/hello.1.gz"], report.unchecked);
+}
+
+#[test]
+fn deb_md5sums_with_digests() {
+    let options = Options {
+        digests: true,
+        ..Options::default()
+    };
+
+    let (_dir, unpack) = unpack("hello_1.0-1_all.deb", &hello_deb(), &options);
+    let report = splayers::verify_md5sums(unpack.root().unwrap()).expect("md5sums");
+    assert!(report.is_ok(), "{:?}", report);
+    assert!(report.unchecked.is_empty(), "{:?}", report);
+}
+
+#[test]
+fn lone_npm_tarball() {
+    let package = tar(&[(
+        "package/package.json",
+        br#"{"name": "left-pad", "version": "1.3.0"}"#,
+    )]);
+    let (_dir, unpack) = unpack("left-pad-1.3.0.tgz", &gz(&package), &Options::default());
+    match unpack.root().and_then(|root| root.package.as_ref()) {
+        Some(Package::Npm(info)) => {
+            assert_eq!("left-pad", info.name);
+            assert_eq!("1.3.0", info.version);
+        }
+        other => panic!("not an npm package: {:?}", other),
+    }
+}
+
+#[test]
+fn hard_links_have_their_targets_content() {
+    let content = b"#!/bin/sh\necho hello\n";
+
+    let mut data = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o755);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(0);
+    header.set_cksum();
+    data.append_data(&mut header, "./usr/bin/hello", &content[..])
+        .unwrap();
+    let mut link = tar::Header::new_gnu();
+    link.set_entry_type(tar::EntryType::Link);
+    link.set_size(0);
+    link.set_mode(0o755);
+    link.set_uid(0);
+    link.set_gid(0);
+    link.set_mtime(0);
+    data.append_link(&mut link, "./usr/bin/hi", "./usr/bin/hello")
+        .unwrap();
+    let data = data.into_inner().unwrap();
+
+    let control = tar(&[
+        ("./control", b"Package: hello\nVersion: 1.0-1\n"),
+        (
+            "./md5sums",
+            b"d604a220708aa59433ba410986cd4ffa  usr/bin/hello\nd604a220708aa59433ba410986cd4ffa  usr/bin/hi\n",
+        ),
+    ]);
+    let mut deb = ar::Builder::new(Vec::new());
+    for (name, content) in &[
+        ("debian-binary", &b"2.0\n"[..]),
+        ("control.tar", &control[..]),
+        ("data.tar", &data[..]),
+    ] {
+        let header = ar::Header::new(name.as_bytes().to_vec(), content.len() as u64);
+        deb.append(&header, *content).unwrap();
+    }
+
+    let (_dir, unpack) = unpack(
+        "hello_1.0-1_all.deb",
+        &deb.into_inner().unwrap(),
+        &Options::default(),
+    );
+    let report = splayers::verify_md5sums(unpack.root().unwrap()).expect("md5sums");
+    assert!(report.is_ok(), "{:?}", report);
+    assert!(report.unchecked.is_empty(), "{:?}", report);
+}
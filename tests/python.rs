@@ -0,0 +1,196 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use sha2::Digest;
+use splayers::Options;
+use splayers::Package;
+use splayers::PythonInfo;
+use splayers::PythonKind;
+use splayers::Unpack;
+
+fn unpack(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &Options::default()).unwrap();
+    (dir, unpack)
+}
+
+fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, data) in files {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// `RECORD`'s `sha256=` form: unpadded, url-safe base64.
+fn record_hash(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let digest = sha2::Sha256::digest(data);
+    let mut out = String::from("sha256=");
+    for chunk in digest.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(char::from(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize]));
+        }
+    }
+    out
+}
+
+fn python_info(unpack: &Unpack) -> Option<&PythonInfo> {
+    match unpack.root().unwrap().package {
+        Some(Package::Python(ref info)) => Some(info),
+        _ => None,
+    }
+}
+
+const METADATA: &[u8] = b"Metadata-Version: 2.1
+Name: hello
+Version: 1.0
+Summary: Says hello
+Requires-Python: >=3.7
+Requires-Dist: requests
+Requires-Dist: click (>=7)
+Classifier: Programming Language :: Python :: 3
+";
+
+const WHEEL: &[u8] = b"Wheel-Version: 1.0
+Root-Is-Purelib: true
+Tag: py2-none-any
+Tag: py3-none-any
+";
+
+const INIT: &[u8] = b"print('hello')\n";
+
+fn wheel(record: &str, extra: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut files: Vec<(&str, &[u8])> = vec![
+        ("hello/__init__.py", INIT),
+        ("hello-1.0.dist-info/METADATA", METADATA),
+        ("hello-1.0.dist-info/WHEEL", WHEEL),
+        ("hello-1.0.dist-info/RECORD", record.as_bytes()),
+    ];
+    files.extend_from_slice(extra);
+    zip(&files)
+}
+
+#[test]
+fn wheel_metadata_and_tags() {
+    let (_dir, unpack) = unpack("hello-1.0-py3-none-any.whl", &wheel("", &[]));
+    let info = python_info(&unpack).expect("a wheel");
+    assert_eq!(PythonKind::Wheel, info.kind);
+    assert_eq!("hello", info.name);
+    assert_eq!("1.0", info.version);
+    assert_eq!("Says hello", info.summary);
+    assert_eq!(">=3.7", info.requires_python);
+    assert_eq!(vec!["requests", "click (>=7)"], info.requires_dist);
+    assert_eq!(1, info.classifiers.len());
+    assert_eq!(vec!["py2-none-any", "py3-none-any"], info.tags);
+    assert_eq!("hello-1.0.dist-info", info.metadata_dir);
+}
+
+#[test]
+fn wheel_record() {
+    let record = format!(
+        "hello/__init__.py,{},{}\n\
+         \"hello-1.0.dist-info/METADATA\",{},{}\n\
+         hello-1.0.dist-info/WHEEL,sha256=wrong,{}\n\
+         hello/gone.py,{},1\n\
+         hello-1.0.dist-info/RECORD,,\n",
+        record_hash(INIT),
+        INIT.len(),
+        record_hash(METADATA),
+        METADATA.len(),
+        WHEEL.len(),
+        record_hash(b"x"),
+    );
+    let (_dir, unpack) = unpack(
+        "hello-1.0-py3-none-any.whl",
+        &wheel(&record, &[("hello/extra.py", b"")]),
+    );
+
+    let report = splayers::verify_record(unpack.root().unwrap()).expect("a RECORD");
+    assert_eq!(vec!["hello-1.0.dist-info/WHEEL"], report.mismatched);
+    assert_eq!(vec!["hello/gone.py"], report.missing);
+    assert_eq!(vec!["hello/extra.py"], report.extra);
+    assert!(report.unchecked.is_empty(), "{:?}", report);
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn sdist() {
+    let (_dir, unpack) = unpack(
+        "hello-1.0.tar.gz",
+        &tar_gz(&[
+            ("hello-1.0/PKG-INFO", METADATA),
+            ("hello-1.0/setup.py", b"from setuptools import setup\n"),
+            ("hello-1.0/hello.egg-info/PKG-INFO", METADATA),
+        ]),
+    );
+    let info = python_info(&unpack).expect("an sdist");
+    assert_eq!(PythonKind::Sdist, info.kind);
+    assert_eq!("hello", info.name);
+    assert_eq!("hello-1.0", info.metadata_dir);
+}
+
+#[test]
+fn pkg_info_beside_other_top_level_dirs_isnt_an_sdist() {
+    let (_dir, unpack) = unpack(
+        "vendored.tar.gz",
+        &tar_gz(&[
+            ("hello-1.0/PKG-INFO", METADATA),
+            ("src/main.c", b"int main() {}\n"),
+        ]),
+    );
+    assert!(python_info(&unpack).is_none());
+}
+
+#[test]
+fn egg() {
+    let (_dir, unpack) = unpack(
+        "hello-1.0-py3.7.egg",
+        &zip(&[("hello/__init__.py", INIT), ("EGG-INFO/PKG-INFO", METADATA)]),
+    );
+    let info = python_info(&unpack).expect("an egg");
+    assert_eq!(PythonKind::Egg, info.kind);
+    assert_eq!("1.0", info.version);
+    assert_eq!("EGG-INFO", info.metadata_dir);
+}
+
+#[test]
+fn empty_candidate_doesnt_hide_later_ones() {
+    let (_dir, unpack) = unpack(
+        "hello-1.0-py3.7.egg",
+        &zip(&[
+            ("stale-0.1.dist-info/METADATA", b""),
+            ("EGG-INFO/PKG-INFO", METADATA),
+        ]),
+    );
+    let info = python_info(&unpack).expect("an egg");
+    assert_eq!(PythonKind::Egg, info.kind);
+}
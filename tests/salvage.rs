@@ -0,0 +1,136 @@
+use std::fs;
+use std::io::Write;
+
+use splayers::Entry;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+fn salvage(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let options = Options {
+        salvage: true,
+        ..Options::default()
+    };
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &options).unwrap();
+    (dir, unpack)
+}
+
+/// The names and content of what was salvaged, which must only be part of the archive.
+fn partial(status: &Status) -> Vec<(String, Vec<u8>)> {
+    match status {
+        Status::Partial(entries, _) => entries.iter().map(name_and_content).collect(),
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+fn name_and_content(entry: &Entry) -> (String, Vec<u8>) {
+    let content = match entry.local.temp {
+        Some(ref temp) => fs::read(temp).unwrap(),
+        None => Vec::new(),
+    };
+    (
+        String::from_utf8_lossy(&entry.local.path).to_string(),
+        content,
+    )
+}
+
+fn files() -> Vec<(String, Vec<u8>)> {
+    (1..=3)
+        .map(|i| {
+            let content = format!("the content of file {}\n", i).repeat(40 * i);
+            (format!("file-{}.txt", i), content.into_bytes())
+        })
+        .collect()
+}
+
+fn tar(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, &content[..])
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn truncated_tar() {
+    let files = files();
+    let whole = tar(&files);
+    // part way through the last file's content
+    let cut = whole.len() - 1024 - 512 - 100;
+
+    let (_dir, unpack) = salvage("cut.tar", &whole[..cut]);
+    assert_eq!(files[..2], partial(unpack.status())[..]);
+
+    // without salvage, nothing
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cut.tar");
+    fs::write(&path, &whole[..cut]).unwrap();
+    let unpack = Unpack::unpack_into(&path, dir.path()).unwrap();
+    assert!(matches!(unpack.status(), Status::Error(_)));
+}
+
+#[test]
+fn truncated_gz() {
+    let text = b"a line of a log file, which was being compressed\n".repeat(500);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(&text).unwrap();
+    let whole = encoder.finish().unwrap();
+
+    let (_dir, unpack) = salvage("cut.log.gz", &whole[..whole.len() / 2]);
+    let salvaged = partial(unpack.status());
+    assert_eq!(1, salvaged.len());
+    let (_, content) = &salvaged[0];
+    assert!(!content.is_empty());
+    assert!(text.starts_with(content));
+}
+
+/// Members as a streaming writer makes them: deflated, with a data descriptor after each.
+fn streamed_zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = Vec::new();
+    for (name, content) in files {
+        zip.extend_from_slice(b"PK\x03\x04");
+        // version; flags: sizes in a data descriptor; deflated; time and date
+        zip.extend_from_slice(&[20, 0, 8, 0, 8, 0, 0, 0, 0x21, 0]);
+        // crc and sizes, all unknown here
+        zip.extend_from_slice(&[0; 12]);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name.as_bytes());
+
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(content).unwrap();
+        let deflated = encoder.finish().unwrap();
+        zip.extend_from_slice(&deflated);
+
+        let mut crc = flate2::Crc::new();
+        crc.update(content);
+        zip.extend_from_slice(b"PK\x07\x08");
+        zip.extend_from_slice(&crc.sum().to_le_bytes());
+        zip.extend_from_slice(&(deflated.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    }
+    zip
+}
+
+#[test]
+fn truncated_streamed_zip() {
+    let files = files();
+    let whole = streamed_zip(&files);
+
+    // the central directory never got written, and nor did all of the last member
+    let (_dir, unpack) = salvage("cut.zip", &whole[..whole.len() - 30]);
+    assert_eq!(files[..2], partial(unpack.status())[..]);
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use serde_json::Value;
+use splayers::Options;
+use splayers::Unpack;
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn deb() -> Vec<u8> {
+    let control = tar(&[(
+        "./control",
+        b"Package: hello\nVersion: 1.0-1\nArchitecture: amd64\n",
+    )]);
+    let data = tar(&[
+        ("./usr/bin/hello", b"#!/bin/sh\necho hello\n"),
+        ("./usr/share/doc/hello/README", b"hello, world\n"),
+    ]);
+
+    let mut deb = ar::Builder::new(Vec::new());
+    for (name, content) in &[
+        ("debian-binary", &b"2.0\n"[..]),
+        ("control.tar", &control[..]),
+        ("data.tar", &data[..]),
+    ] {
+        let header = ar::Header::new(name.as_bytes().to_vec(), content.len() as u64);
+        deb.append(&header, *content).unwrap();
+    }
+    deb.into_inner().unwrap()
+}
+
+/// A jar whose manifest doesn't name it.
+fn nameless_jar() -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("META-INF/MANIFEST.MF", options).unwrap();
+    writer.write_all(b"Manifest-Version: 1.0\r\n").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn unpack_bundle() -> (tempfile::TempDir, Unpack) {
+    let bundle = tar(&[
+        ("hello_1.0-1_amd64.deb", &deb()),
+        ("lib/thing.jar", &nameless_jar()),
+        ("NOTES", b"nothing to see\n"),
+    ]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bundle.tar");
+    fs::write(&path, bundle).unwrap();
+    let options = Options {
+        digests: true,
+        ..Options::default()
+    };
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), &options).unwrap();
+    (dir, unpack)
+}
+
+fn strs<'v>(values: &'v Value, key: &str) -> Vec<&'v str> {
+    values
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value[key].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn spdx() {
+    let (_dir, unpack) = unpack_bundle();
+    let document: Value = serde_json::from_str(&unpack.spdx_json("bundle")).unwrap();
+    assert_eq!("SPDX-2.3", document["spdxVersion"]);
+
+    let packages = &document["packages"];
+    let files = &document["files"];
+    let names = strs(packages, "name");
+    assert_eq!(vec!["bundle", "hello", "bundle.tar/lib/thing.jar"], names);
+
+    let deb = &packages[1];
+    assert_eq!("1.0-1", deb["versionInfo"]);
+    assert_eq!(
+        "pkg:deb/debian/hello@1.0-1?arch=amd64",
+        deb["externalRefs"][0]["referenceLocator"]
+    );
+
+    let file_names = strs(files, "fileName");
+    assert!(file_names.contains(&"./bundle.tar"), "{:?}", file_names);
+    assert!(
+        file_names.contains(&"./bundle.tar/hello_1.0-1_amd64.deb/data.tar/usr/bin/hello"),
+        "{:?}",
+        file_names
+    );
+    assert!(files
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|file| 3 == file["checksums"].as_array().unwrap().len()));
+
+    let mut ids: Vec<&str> = strs(packages, "SPDXID");
+    ids.extend(strs(files, "SPDXID"));
+    let unique: HashSet<&str> = ids.iter().cloned().collect();
+    assert_eq!(ids.len(), unique.len(), "{:?}", ids);
+
+    let relationships = document["relationships"].as_array().unwrap();
+    let id_of = |name: &str| {
+        packages
+            .as_array()
+            .unwrap()
+            .iter()
+            .chain(files.as_array().unwrap())
+            .find(|element| element["name"] == name || element["fileName"] == format!("./{}", name))
+            .map(|element| element["SPDXID"].as_str().unwrap().to_string())
+            .unwrap_or_else(|| panic!("no {}", name))
+    };
+    let contains = |from: &str, to: &str| {
+        relationships.iter().any(|relationship| {
+            relationship["spdxElementId"] == from
+                && relationship["relationshipType"] == "CONTAINS"
+                && relationship["relatedSpdxElement"] == to
+        })
+    };
+
+    assert!(contains("SPDXRef-Root", &id_of("bundle.tar")));
+    assert!(contains(&id_of("bundle.tar"), &id_of("hello")));
+    assert!(contains(
+        &id_of("hello"),
+        &id_of("bundle.tar/hello_1.0-1_amd64.deb/data.tar/usr/bin/hello")
+    ));
+    assert!(contains(&id_of("bundle.tar"), &id_of("bundle.tar/NOTES")));
+
+    // everything but the document's description is a containment of something listed
+    for relationship in &relationships[1..] {
+        assert!(unique.contains(relationship["spdxElementId"].as_str().unwrap()));
+        assert!(unique.contains(relationship["relatedSpdxElement"].as_str().unwrap()));
+    }
+    assert_eq!(ids.len(), relationships.len());
+}
+
+#[test]
+fn cyclonedx() {
+    let (_dir, unpack) = unpack_bundle();
+    let document: Value = serde_json::from_str(&unpack.cyclonedx_json("bundle")).unwrap();
+    assert_eq!("CycloneDX", document["bomFormat"]);
+    assert_eq!("bundle", document["metadata"]["component"]["name"]);
+
+    let top = document["components"].as_array().unwrap();
+    assert_eq!(vec!["bundle.tar"], strs(&document["components"], "name"));
+    let bundle = &top[0]["components"];
+    assert_eq!(
+        vec!["hello", "bundle.tar/lib/thing.jar", "bundle.tar/NOTES"],
+        strs(bundle, "name")
+    );
+
+    let deb = &bundle[0];
+    assert_eq!("library", deb["type"]);
+    assert_eq!("pkg:deb/debian/hello@1.0-1?arch=amd64", deb["purl"]);
+    let in_deb = strs(&deb["components"], "name");
+    assert!(
+        in_deb.contains(&"bundle.tar/hello_1.0-1_amd64.deb/data.tar/usr/bin/hello"),
+        "{:?}",
+        in_deb
+    );
+
+    let mut refs = Vec::new();
+    fn collect<'v>(components: &'v Value, refs: &mut Vec<&'v str>) {
+        for component in components.as_array().into_iter().flatten() {
+            refs.push(component["bom-ref"].as_str().unwrap());
+            collect(&component["components"], refs);
+        }
+    }
+    collect(&document["components"], &mut refs);
+    let unique: HashSet<&str> = refs.iter().cloned().collect();
+    assert_eq!(refs.len(), unique.len(), "{:?}", refs);
+}
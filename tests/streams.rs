@@ -0,0 +1,352 @@
+use std::fs;
+use std::io::Write;
+
+use splayers::FileType;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+fn unpack(name: &str, data: &[u8]) -> (tempfile::TempDir, Unpack) {
+    unpack_with(name, data, &Options::default())
+}
+
+fn unpack_with(name: &str, data: &[u8], options: &Options) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), options).unwrap();
+    (dir, unpack)
+}
+
+/// The only stream unpacked from `status`, which must be kept, as it isn't unpackable.
+fn only_stream(status: &Status) -> Vec<u8> {
+    match status {
+        Status::Success(entries) if 1 == entries.len() => {
+            fs::read(entries[0].local.temp.as_ref().expect("stream kept")).unwrap()
+        }
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+fn gz(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::none());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn gz_member_ending_just_before_a_buffer_boundary() {
+    // stored, so a member grows with its text: find the text which makes it 8191 bytes
+    let text = "first member, ".repeat(1000).into_bytes();
+    let first = (8191 - 64..8191)
+        .map(|len| &text[..len])
+        .find(|first| 8191 == gz(first).len())
+        .unwrap();
+    let second = b"second member\n";
+
+    let mut data = gz(first);
+    data.extend(gz(second));
+
+    let (_dir, unpack) = unpack("members.gz", &data);
+    assert_eq!([first, &second[..]].concat(), only_stream(unpack.status()));
+}
+
+/// Brotli with the data stored, in one uncompressed meta-block.
+fn stored_brotli(data: &[u8]) -> Vec<u8> {
+    assert!(!data.is_empty() && data.len() <= 0x1_0000);
+    // a 64KiB window; not the last meta-block; four nibbles of length; uncompressed
+    let header = ((data.len() as u32 - 1) << 4) | (1 << 20);
+    let mut stream = header.to_le_bytes()[..3].to_vec();
+    stream.extend_from_slice(data);
+    // the last meta-block, empty
+    stream.push(0b11);
+    stream
+}
+
+#[test]
+fn brotli_round_trip() {
+    // '?' is a complete, empty, brotli stream, so a stream mustn't be tried as one again
+    for text in [&b"fresh notes\n"[..], b"?"] {
+        let (_dir, unpack) = unpack("notes.txt.br", &stored_brotli(text));
+        assert_eq!(text, &only_stream(unpack.status())[..]);
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn hints_are_checked() {
+    let text = b"some notes, which were deflated\n".repeat(10);
+
+    let (_dir, deflated) = unpack("notes.deflate", &deflate(&text));
+    let root = deflated.root().unwrap();
+    assert_eq!("deflate", root.type_hint.as_ref().unwrap().extension);
+    assert_eq!(text, only_stream(deflated.status()));
+
+    // a stream which stops before the end of the file, or which decodes to nothing
+    let mut trailing = deflate(&text);
+    trailing.extend_from_slice(b"more");
+    let unused = [("notes.deflate", trailing), ("notes.br", b"?".to_vec())];
+    for (name, data) in &unused {
+        let (_dir, unpack) = unpack(name, data);
+        let root = unpack.root().unwrap();
+        assert!(root.type_hint.is_none(), "{}", name);
+        assert!(root.local.temp.is_some(), "{}", name);
+        assert!(!matches!(unpack.status(), Status::Success(_)), "{}", name);
+    }
+}
+
+#[test]
+fn wrong_extension_changes_nothing() {
+    let text = b"some notes, which were deflated\n".repeat(10);
+    let mut trailing = deflate(&text);
+    trailing.extend_from_slice(b"more");
+
+    for data in &[text.clone(), trailing] {
+        let outcome = |name: &str| {
+            let (_dir, unpack) = unpack(name, data);
+            let root = unpack.root().unwrap();
+            let status = format!("{:?}", unpack.status());
+            let hinted = root.type_hint.is_some();
+            let kept = fs::read(root.local.temp.as_ref().expect("content kept")).unwrap();
+
+            // including any a failed attempt left behind
+            let temps = walkdir::WalkDir::new(unpack.into_path())
+                .into_iter()
+                .filter(|entry| entry.as_ref().unwrap().file_type().is_file())
+                .count();
+            (status, hinted, kept, temps)
+        };
+
+        let plain = outcome("notes");
+        assert!(!plain.1);
+        assert_eq!(plain, outcome("notes.gz"));
+        assert_eq!(plain, outcome("notes.deflate"));
+    }
+}
+
+/// The codes of Unix `compress`, packed as `ncompress` packs them.
+struct Codes {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+    width: u32,
+    /// Codes written at the current width, mod 8.
+    group: u32,
+}
+
+impl Codes {
+    fn put(&mut self, code: u32) {
+        self.bits |= u64::from(code) << self.count;
+        self.count += self.width;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+        self.group = (self.group + 1) % 8;
+    }
+
+    /// Codes grow at the end of a group of eight, padded out if need be.
+    fn widen(&mut self) {
+        while 0 != self.group {
+            self.put(0);
+        }
+        self.width += 1;
+    }
+}
+
+/// `compress -b16`, less clearing the table when it fills.
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut codes = Codes {
+        out: vec![0x1f, 0x9d, 0x80 | 16],
+        bits: 0,
+        count: 0,
+        width: 9,
+        group: 0,
+    };
+    let mut table = std::collections::HashMap::new();
+    let mut next = 257;
+    let mut prefix = u32::from(data[0]);
+    for &byte in &data[1..] {
+        if let Some(&code) = table.get(&(prefix, byte)) {
+            prefix = code;
+            continue;
+        }
+        codes.put(prefix);
+        if next >= 1 << codes.width && codes.width < 16 {
+            codes.widen();
+        }
+        if next < 1 << 16 {
+            table.insert((prefix, byte), next);
+            next += 1;
+        }
+        prefix = u32::from(byte);
+    }
+    codes.put(prefix);
+    if 0 != codes.count {
+        codes.out.push(codes.bits as u8);
+    }
+    codes.out
+}
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn compress_round_trip() {
+    // enough distinct strings for the codes to grow to 11 bits, and runs for KwKwK codes
+    let mut text = Vec::new();
+    for i in 0..3000u32 {
+        text.extend_from_slice(format!("{} ", i * 7919 % 10007).as_bytes());
+    }
+    text.extend_from_slice(&[b'z'; 100]);
+
+    let (_dir, stream) = unpack("numbers.Z", &compress(&text));
+    assert_eq!(text, only_stream(stream.status()));
+
+    let (_dir, tarball) = unpack("numbers.tar.Z", &compress(&tar(&[("numbers", &text)])));
+    match tarball.status() {
+        Status::Success(entries) => {
+            let paths: Vec<_> = entries.iter().map(|entry| &entry.local.path[..]).collect();
+            assert_eq!(vec![&b"numbers"[..]], paths);
+        }
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+/// An lzop file, `lzop -1` with Adler-32s, holding `blocks` of (decoded, encoded).
+fn lzop(blocks: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut data = b"\x89LZO\0\r\n\x1a\n".to_vec();
+    // version, library version, version needed, method, level
+    data.extend_from_slice(&[0x10, 0x30, 0x20, 0x80, 0x09, 0x40, 1, 1]);
+    // flags: Adler-32s of the decoded and encoded data
+    data.extend_from_slice(&3u32.to_be_bytes());
+    // mode, the two halves of the mtime
+    data.extend_from_slice(&0o100_644u32.to_be_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.push(9);
+    data.extend_from_slice(b"notes.txt");
+    // the header's checksum, which isn't checked
+    data.extend_from_slice(&[0; 4]);
+
+    for (decoded, encoded) in blocks {
+        data.extend_from_slice(&(decoded.len() as u32).to_be_bytes());
+        data.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        // checksums, of the encoded data only if it isn't stored
+        let checksums = if decoded.len() == encoded.len() { 1 } else { 2 };
+        data.extend(std::iter::repeat_n(0xcc, 4 * checksums));
+        data.extend_from_slice(encoded);
+    }
+    data.extend_from_slice(&[0; 4]);
+    data
+}
+
+#[test]
+fn lzop_round_trip() {
+    let lzo1x: &[u8] = &[
+        // a literal run to start, "abc"
+        17 + 3,
+        b'a',
+        b'b',
+        b'c',
+        // a 20 byte match, 3 back, and no literals after
+        32 | (20 - 2),
+        (3 - 1) << 2,
+        0,
+        // four literals, after none
+        4 - 3,
+        b'w',
+        b'x',
+        b'y',
+        b'z',
+        // a 4 byte match, 4 back, then two literals
+        ((4 - 1) << 5) | ((4 - 1) << 2) | 2,
+        0,
+        b'!',
+        b'\n',
+        // the end
+        0x11,
+        0,
+        0,
+    ];
+    let first = b"abcabcabcabcabcabcabcabwxyzwxyz!\n";
+    let second = b"stored, as it didn't compress\n";
+
+    let (_dir, unpack) = unpack("notes.txt.lzo", &lzop(&[(first, lzo1x), (second, second)]));
+    assert_eq!(
+        [&first[..], &second[..]].concat(),
+        only_stream(unpack.status())
+    );
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn bare_zlib_streams() {
+    let text = b"some notes, which were zlib'd\n".repeat(10);
+    let stream = zlib(&text);
+    assert_eq!(
+        FileType::Zlib,
+        splayers::identify(&stream, None, None).file_type
+    );
+
+    let (_dir, inflated) = unpack("notes", &stream);
+    assert_eq!(text, only_stream(inflated.status()));
+
+    // unless turned off
+    let options = Options {
+        zlib_streams: false,
+        ..Options::default()
+    };
+    let (_dir, kept) = unpack_with("notes", &stream, &options);
+    assert!(!matches!(kept.status(), Status::Success(_)));
+}
+
+#[test]
+fn zlib_header_alone_isnt_enough() {
+    let mut reserved_block = vec![0x78, 0x9c, 0xff];
+    reserved_block.resize(200, 0x42);
+
+    // a stored block whose length isn't followed by its complement
+    let mut stored = vec![0x78, 0x9c, 0x01, 0x05, 0x00, 0x05, 0x00];
+    stored.resize(200, 0x42);
+
+    // the stream is fine, but needs a preset dictionary we don't have
+    let mut dictionary = vec![0x78, 0xbb, 0, 0, 0, 0];
+    dictionary.extend_from_slice(&zlib(b"some notes\n")[2..]);
+    dictionary.resize(200, 0);
+
+    // the header check fails
+    let mut unchecked = zlib(&b"some notes\n".repeat(10));
+    unchecked[1] ^= 1;
+
+    for header in &[reserved_block, stored, dictionary, unchecked] {
+        let identified = splayers::identify(header, None, None);
+        assert_ne!(FileType::Zlib, identified.file_type, "{:x?}", &header[..8]);
+
+        let (_dir, unpack) = unpack("notes", header);
+        assert!(unpack.root().unwrap().local.temp.is_some());
+        assert!(!matches!(unpack.status(), Status::Success(_)));
+    }
+}
@@ -0,0 +1,349 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use aes::cipher::BlockEncrypt;
+use aes::cipher::KeyInit;
+use hmac::Mac;
+use splayers::Entry;
+use splayers::Options;
+use splayers::Status;
+use splayers::Unpack;
+
+fn unpack(name: &str, data: &[u8], options: &Options) -> (tempfile::TempDir, Unpack) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    fs::write(&path, data).unwrap();
+    let unpack = Unpack::unpack_into_with(&path, dir.path(), options).unwrap();
+    (dir, unpack)
+}
+
+fn entries(status: &Status) -> &[Entry] {
+    match status {
+        Status::Success(entries) => entries,
+        other => panic!("unexpected status: {:?}", other),
+    }
+}
+
+fn content(entry: &Entry) -> Option<Vec<u8>> {
+    entry
+        .local
+        .temp
+        .as_ref()
+        .map(|temp| fs::read(temp).unwrap())
+}
+
+/// A stored member, as it's written: its data already encrypted, if it is.
+struct Member {
+    name: &'static [u8],
+    flags: u16,
+    method: u16,
+    crc: u32,
+    size: u32,
+    extra: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl Member {
+    fn plain(name: &'static [u8], data: &[u8]) -> Member {
+        Member {
+            name,
+            flags: 0,
+            method: 0,
+            crc: crc32(data),
+            size: data.len() as u32,
+            extra: Vec::new(),
+            data: data.to_vec(),
+        }
+    }
+}
+
+fn zip(members: &[Member]) -> Vec<u8> {
+    let mut file = Vec::new();
+    let mut central = Vec::new();
+    for member in members {
+        let offset = file.len() as u32;
+        let fields = |to: &mut Vec<u8>| {
+            to.extend_from_slice(&51u16.to_le_bytes());
+            to.extend_from_slice(&member.flags.to_le_bytes());
+            to.extend_from_slice(&member.method.to_le_bytes());
+            // 1980-01-01 00:00
+            to.extend_from_slice(&[0, 0, 0x21, 0]);
+            to.extend_from_slice(&member.crc.to_le_bytes());
+            to.extend_from_slice(&(member.data.len() as u32).to_le_bytes());
+            to.extend_from_slice(&member.size.to_le_bytes());
+            to.extend_from_slice(&(member.name.len() as u16).to_le_bytes());
+            to.extend_from_slice(&(member.extra.len() as u16).to_le_bytes());
+        };
+
+        file.extend_from_slice(b"PK\x03\x04");
+        fields(&mut file);
+        file.extend_from_slice(member.name);
+        file.extend_from_slice(&member.extra);
+        file.extend_from_slice(&member.data);
+
+        central.extend_from_slice(b"PK\x01\x02");
+        central.extend_from_slice(&51u16.to_le_bytes());
+        fields(&mut central);
+        // comment length, disk, internal and external attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(member.name);
+        central.extend_from_slice(&member.extra);
+    }
+
+    let central_offset = file.len() as u32;
+    file.extend_from_slice(&central);
+    file.extend_from_slice(b"PK\x05\x06\0\0\0\0");
+    file.extend_from_slice(&(members.len() as u16).to_le_bytes());
+    file.extend_from_slice(&(members.len() as u16).to_le_bytes());
+    file.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    file.extend_from_slice(&central_offset.to_le_bytes());
+    file.extend_from_slice(&[0, 0]);
+    file
+}
+
+fn crc32_byte(crc: u32, byte: u8) -> u32 {
+    let mut c = (crc ^ u32::from(byte)) & 0xff;
+    for _ in 0..8 {
+        c = if 1 == c & 1 {
+            0xedb8_8320 ^ (c >> 1)
+        } else {
+            c >> 1
+        };
+    }
+    (crc >> 8) ^ c
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| crc32_byte(crc, byte))
+}
+
+/// The traditional PKWARE cipher's state, after the password.
+struct ZipCrypto([u32; 3]);
+
+impl ZipCrypto {
+    fn new(password: &[u8]) -> ZipCrypto {
+        let mut keys = ZipCrypto([0x1234_5678, 0x2345_6789, 0x3456_7890]);
+        for &byte in password {
+            keys.update(byte);
+        }
+        keys
+    }
+
+    fn update(&mut self, plain: u8) {
+        let keys = &mut self.0;
+        keys[0] = crc32_byte(keys[0], plain);
+        keys[1] = (keys[1].wrapping_add(keys[0] & 0xff))
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        keys[2] = crc32_byte(keys[2], (keys[1] >> 24) as u8);
+    }
+
+    fn stream(&self) -> u8 {
+        let temp = (self.0[2] | 2) & 0xffff;
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    fn encrypt(&mut self, plain: u8) -> u8 {
+        let cipher = plain ^ self.stream();
+        self.update(plain);
+        cipher
+    }
+
+    fn decrypt(&mut self, cipher: u8) -> u8 {
+        let plain = cipher ^ self.stream();
+        self.update(plain);
+        plain
+    }
+}
+
+fn zip_crypto(name: &'static [u8], password: &[u8], data: &[u8]) -> Member {
+    let crc = crc32(data);
+    // the last byte of the header is the check: the top of the crc
+    let mut header = *b"not random.\0";
+    header[11] = (crc >> 24) as u8;
+
+    let mut keys = ZipCrypto::new(password);
+    let encrypted = header
+        .iter()
+        .chain(data)
+        .map(|&byte| keys.encrypt(byte))
+        .collect();
+
+    Member {
+        name,
+        flags: 1,
+        method: 0,
+        crc,
+        size: data.len() as u32,
+        extra: Vec::new(),
+        data: encrypted,
+    }
+}
+
+/// WinZip's AE-2, with AES-256: no crc, but a MAC.
+fn winzip_aes(name: &'static [u8], password: &[u8], data: &[u8]) -> Member {
+    let salt = [7u8; 16];
+    let mut keys = [0u8; 66];
+    pbkdf2::pbkdf2::<hmac::Hmac<sha1::Sha1>>(password, &salt, 1000, &mut keys);
+
+    // counter mode, with a little endian counter starting from one
+    let cipher = aes::Aes256::new_from_slice(&keys[..32]).unwrap();
+    let mut encrypted = data.to_vec();
+    for (counter, chunk) in encrypted.chunks_mut(16).enumerate() {
+        let mut block = [0u8; 16];
+        block[..8].copy_from_slice(&(counter as u64 + 1).to_le_bytes());
+        let mut block = block.into();
+        cipher.encrypt_block(&mut block);
+        for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+            *byte ^= key;
+        }
+    }
+
+    let mut mac = <hmac::Hmac<sha1::Sha1> as Mac>::new_from_slice(&keys[32..64]).unwrap();
+    mac.update(&encrypted);
+
+    let mut written = salt.to_vec();
+    written.extend_from_slice(&keys[64..]);
+    written.extend_from_slice(&encrypted);
+    written.extend_from_slice(&mac.finalize().into_bytes()[..10]);
+
+    Member {
+        name,
+        flags: 1,
+        method: 99,
+        crc: 0,
+        size: data.len() as u32,
+        // AE-2, "AE", AES-256, really stored
+        extra: vec![0x01, 0x99, 7, 0, 2, 0, b'A', b'E', 3, 0, 0],
+        data: written,
+    }
+}
+
+fn with_password(password: &[u8]) -> Options {
+    let password = password.to_vec();
+    Options {
+        password: Some(Box::new(move |_: &[u8]| Some(password.clone()))),
+        ..Options::default()
+    }
+}
+
+#[test]
+fn encrypted_members() {
+    let secret = b"the secret plans\n".repeat(10);
+    let file = zip(&[
+        Member::plain(b"readme.txt", b"nothing to see here\n"),
+        zip_crypto(b"classic.txt", b"hunter2", &secret),
+        winzip_aes(b"aes.txt", b"hunter2", &secret),
+    ]);
+
+    for options in &[Options::default(), with_password(b"swordfish")] {
+        let (_dir, unpack) = unpack("secrets.zip", &file, options);
+        let entries = entries(unpack.status());
+        assert_eq!(3, entries.len());
+        assert_eq!(
+            Some(b"nothing to see here\n".to_vec()),
+            content(&entries[0])
+        );
+        for (entry, encryption) in entries[1..]
+            .iter()
+            .zip(&[splayers::Encryption::ZipCrypto, splayers::Encryption::Aes])
+        {
+            assert_eq!(Some(*encryption), entry.local.meta.encryption);
+            assert_eq!(None, content(entry));
+            assert!(matches!(entry.children, Status::Encrypted));
+        }
+    }
+
+    let (_dir, unpack) = unpack("secrets.zip", &file, &with_password(b"hunter2"));
+    let entries = entries(unpack.status());
+    for (entry, encryption) in entries[1..]
+        .iter()
+        .zip(&[splayers::Encryption::ZipCrypto, splayers::Encryption::Aes])
+    {
+        assert_eq!(Some(*encryption), entry.local.meta.encryption);
+        assert_eq!(Some(secret.clone()), content(entry));
+    }
+}
+
+#[test]
+fn wrong_password_passing_the_check_byte() {
+    let secret = b"the secret plans\n".repeat(10);
+    let member = zip_crypto(b"classic.txt", b"hunter2", &secret);
+
+    // one password in 256 decrypts the header to the right check byte
+    let wrong = (0..)
+        .map(|i| format!("wrong{}", i).into_bytes())
+        .find(|password| {
+            let mut keys = ZipCrypto::new(password);
+            let header: Vec<u8> = member.data[..12]
+                .iter()
+                .map(|&byte| keys.decrypt(byte))
+                .collect();
+            header[11] == (member.crc >> 24) as u8
+        })
+        .unwrap();
+
+    let (_dir, unpack) = unpack("secrets.zip", &zip(&[member]), &with_password(&wrong));
+    let entries = entries(unpack.status());
+    assert_eq!(
+        Some(splayers::Encryption::ZipCrypto),
+        entries[0].local.meta.encryption
+    );
+    assert_eq!(None, content(&entries[0]));
+    assert!(matches!(entries[0].children, Status::Encrypted));
+}
+
+fn zip_of(names: impl Iterator<Item = String>, content: &[u8]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for name in names {
+        writer.start_file(name, options).unwrap();
+    }
+    writer.start_file("content.txt", options).unwrap();
+    writer.write_all(content).unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn content_txt(entries: &[Entry]) -> Vec<u8> {
+    let entry = entries
+        .iter()
+        .find(|entry| b"content.txt" == &entry.local.path[..])
+        .expect("content.txt unpacked");
+    content(entry).expect("content kept")
+}
+
+#[test]
+fn zip64_with_prefixed_stub() {
+    // more entries than the classic end of central directory can count
+    let count = 0x1_0000;
+    let mut data = b"#!/bin/sh\nexec java -jar \"$0\" \"$@\"\n".to_vec();
+    data.extend(zip_of(
+        (0..count).map(|i| format!("{:05}", i)),
+        b"hello from the end of a big zip\n",
+    ));
+
+    let (_dir, unpack) = unpack("launcher", &data, &Options::default());
+    let entries = entries(unpack.status());
+    assert_eq!(count + 1, entries.len());
+    assert_eq!(
+        &b"hello from the end of a big zip\n"[..],
+        &content_txt(entries)[..]
+    );
+}
+
+#[test]
+fn spanned_marker() {
+    let text = b"a single segment of a spanned archive\n".repeat(8);
+    for marker in &[&b"PK\x07\x08"[..], &b"PK00"[..]] {
+        let mut data = marker.to_vec();
+        data.extend(zip_of((0..3).map(|i| format!("empty-{}", i)), &text));
+
+        let (_dir, unpack) = unpack("spanned.zip", &data, &Options::default());
+        let entries = entries(unpack.status());
+        assert_eq!(4, entries.len());
+        assert_eq!(&text[..], &content_txt(entries)[..]);
+    }
+}